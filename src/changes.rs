@@ -0,0 +1,172 @@
+//! What an update did to the files, the way people think of it: created, changed, renamed or deleted
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::files::{CacheUpdate, FileCache, FileStore};
+use crate::watching::watcher_keep::WatcherUpdate;
+
+#[derive(Serialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Changed,
+    Renamed,
+    Deleted,
+}
+
+/// One change to a [`FileStore`], with the text the file had before it
+#[derive(Clone, Debug)]
+pub struct Change {
+    kind: ChangeKind,
+    key: PathBuf,
+    from: Option<PathBuf>,
+    previous: Option<String>,
+    // Only text was added, the previous text is what the file now holds up to here
+    appended_to: Option<usize>,
+}
+
+// The text the file at `path` has in the cache, None when it is not listed, Some(None) when not loaded
+fn cached_text(file_cache: &FileCache, path: &Path) -> Option<(PathBuf, Option<String>)> {
+    let key = file_cache
+        .relative_key(path)
+        .filter(|key| file_cache.contains(key))?;
+    let text = file_cache
+        .file_ref(&key)
+        .map(|file| file.file_form_ref().text_ref().to_owned());
+    Some((key, text))
+}
+
+impl Change {
+    fn new(kind: ChangeKind, key: PathBuf) -> Change {
+        Change {
+            kind,
+            key,
+            from: None,
+            previous: None,
+            appended_to: None,
+        }
+    }
+
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    /// The key of the file in the store, where it was moved to for renames
+    pub fn key_ref(&self) -> &PathBuf {
+        &self.key
+    }
+
+    /// The key the file had before it was renamed
+    pub fn from_ref(&self) -> Option<&Path> {
+        self.from.as_deref()
+    }
+
+    /// The text before the change, None when the file was new or not loaded, or only had text added
+    pub fn previous_ref(&self) -> Option<&str> {
+        self.previous.as_deref()
+    }
+
+    /// The text before the change given the `text` the file has now, for text added to the end as well
+    ///
+    /// What is piped in keeps growing, copying all of it for every change would take longer and longer.
+    pub fn previous_text<'a>(&'a self, text: &'a str) -> Option<&'a str> {
+        match self.appended_to {
+            Some(appended_to) => text.get(..appended_to),
+            None => self.previous_ref(),
+        }
+    }
+
+    /// The same change, the way the cache reports it
+    pub fn cache_update(&self) -> CacheUpdate {
+        match (self.kind, &self.from) {
+            (ChangeKind::Renamed, Some(from)) => {
+                CacheUpdate::Renamed(from.clone(), self.key.clone())
+            }
+            (ChangeKind::Deleted, _) => CacheUpdate::Deleted(self.key.clone()),
+            _ => CacheUpdate::Changed(self.key.clone()),
+        }
+    }
+
+    /// Apply `update` to the store, returns the changes it made
+    ///
+    /// Content that did not change is left out, and a file renamed over another one (how editors and `sed -i` save)
+    /// is reported as a delete of the first and a change to the second.
+    pub fn apply(store: &mut FileStore, update: WatcherUpdate) -> Vec<Change> {
+        let file_cache = store.file_cache_ref();
+        // What the file (or the file renamed over) held before
+        let previous = match &update {
+            WatcherUpdate::FileContent(file) => cached_text(file_cache, file.path_ref()),
+            WatcherUpdate::FileTooBig(path) => cached_text(file_cache, path),
+            WatcherUpdate::FileAppend(..) => None,
+            WatcherUpdate::FileRename(rename_event) => {
+                cached_text(file_cache, rename_event.from_and_to_ref().1)
+            }
+            WatcherUpdate::FileDelete(_) => None,
+        };
+        // Where the text added to a file starts, the text before it is not copied
+        let appended_to = match &update {
+            WatcherUpdate::FileAppend(path, _) => file_cache.relative_key(path).and_then(|key| {
+                let file = file_cache.file_ref(&key)?;
+                Some((key, file.file_form_ref().text_ref().len()))
+            }),
+            _ => None,
+        };
+        let previous_of = |key: &Path| {
+            previous
+                .as_ref()
+                .filter(|(previous_key, _)| previous_key == key)
+                .map(|(_, text)| text.clone())
+        };
+
+        let cache_updates = store.apply(update);
+        let file_cache = store.file_cache_ref();
+        let mut changes = Vec::new();
+        for cache_update in cache_updates {
+            match cache_update {
+                CacheUpdate::Changed(key) => {
+                    if let Some((_, appended_to)) = appended_to
+                        .as_ref()
+                        .filter(|(appended, _)| *appended == key)
+                    {
+                        changes.push(Change {
+                            appended_to: Some(*appended_to),
+                            ..Change::new(ChangeKind::Changed, key)
+                        });
+                        continue;
+                    }
+                    let text = file_cache
+                        .file_ref(&key)
+                        .map(|file| file.file_form_ref().text_ref());
+                    let kind = match previous_of(&key) {
+                        // Reloaded without a new text (touched, or reloaded after a rename)
+                        Some(Some(previous)) if Some(previous.as_str()) == text => continue,
+                        Some(_) => ChangeKind::Changed,
+                        None => ChangeKind::Created,
+                    };
+                    let previous = previous_of(&key).flatten();
+                    changes.push(Change {
+                        previous,
+                        ..Change::new(kind, key)
+                    });
+                }
+                CacheUpdate::Renamed(from, to) => match previous_of(&to) {
+                    Some(previous) => {
+                        changes.push(Change::new(ChangeKind::Deleted, from));
+                        changes.push(Change {
+                            previous,
+                            ..Change::new(ChangeKind::Changed, to)
+                        });
+                    }
+                    None => changes.push(Change {
+                        from: Some(from),
+                        ..Change::new(ChangeKind::Renamed, to)
+                    }),
+                },
+                CacheUpdate::Deleted(key) => changes.push(Change::new(ChangeKind::Deleted, key)),
+            }
+        }
+        changes
+    }
+}
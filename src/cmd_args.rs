@@ -1,21 +1,189 @@
-use crate::err_tools;
-use crate::MagicError;
-use std::env;
-
-// Vec<String>
-pub fn get_arg() -> Result<Option<String>, MagicError> {
-    let mut args = env::args().collect::<Vec<String>>();
-    println!("args!!!!!! <{:?}>", args);
-
-    let len = args.len();
-    let result = match len {
-        1 => Ok(None),
-        2 => Ok(Some(args.remove(1))),
-        _ => {
-            let err_msg = &format!("You can only pass 1 (one) argument!");
-            return Err(err_tools::ErrorX::new_box(err_msg));
+//! The command line of live_watch
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+use ignore::gitignore::GitignoreBuilder;
+
+/// How file contents are laid out
+#[derive(PartialEq, Clone, Copy, Debug, ValueEnum)]
+pub enum TextModeArg {
+    Standard,
+    /// Every line on its own
+    Lines,
+    /// Every line on its own, empty lines included
+    AllLines,
+    /// Highlighted, and copyable with the mouse
+    Selectable,
+}
+
+/// What following changes does with a file that changed
+#[derive(PartialEq, Clone, Copy, Debug, ValueEnum)]
+pub enum FollowArg {
+    /// Open it
+    Switch,
+    /// Flash it in the file list
+    Flash,
+}
+
+/// What JSON events carry besides the path, size and hash
+#[derive(PartialEq, Clone, Copy, Debug, ValueEnum)]
+pub enum InlineArg {
+    /// The whole text of the file
+    Content,
+    /// What changed, as a unified diff
+    Diff,
+}
+
+/// How changes on disk are noticed
+#[derive(PartialEq, Clone, Copy, Debug, ValueEnum)]
+pub enum Backend {
+    /// Kernel notifications, immediate but local filesystems only
+    Inotify,
+    /// Scanning the tree every --poll-interval, works everywhere
+    Poll,
+    /// inotify, unless a path is on a network filesystem (NFS, SSHFS, SMB, FUSE)
+    Auto,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "live_watch",
+    version,
+    about = "Watch files and directories, showing their contents as they change"
+)]
+pub struct Args {
+    /// Files or directories to watch, the current directory when none are given, `-` for what is piped in
+    #[arg(value_name = "PATH")]
+    pub paths: Vec<PathBuf>,
+
+    /// Whether `-` was among the paths, taken out of them by [`parse`]
+    #[arg(skip)]
+    pub stdin: bool,
+
+    /// Print changes (and their diffs) to stdout instead of opening a window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Print changes as JSON Lines (one object per line) instead of opening a window
+    #[arg(long)]
+    pub json: bool,
+
+    /// Put the content or diff of changed files into every JSON event
+    #[arg(long, value_enum, value_name = "WHAT", requires = "json")]
+    pub json_inline: Option<InlineArg>,
+
+    /// Serve the files and their changes over HTTP instead of opening a window (127.0.0.1:8080, 0.0.0.0:8080 for the LAN)
+    #[arg(long, value_name = "ADDR")]
+    pub serve: Option<SocketAddr>,
+
+    /// How file contents are laid out
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub text_mode: Option<TextModeArg>,
+
+    /// Font size of file contents, in points
+    #[arg(long, value_name = "SIZE")]
+    pub font_size: Option<f32>,
+
+    /// Do not wrap long lines
+    #[arg(long)]
+    pub no_wrap: bool,
+
+    /// Follow changes: switch to (or flash) files as they change
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub follow: Option<FollowArg>,
+
+    /// Keep views scrolled to the end of their file as it grows
+    #[arg(long)]
+    pub tail: bool,
+
+    /// Leave out paths matching GLOB (gitignore syntax, can be given more than once)
+    #[arg(long = "ignore", value_name = "GLOB")]
+    pub ignore_globs: Vec<String>,
+
+    /// Milliseconds the tree has to stay quiet before triggers run and headless output is printed [default: 300]
+    #[arg(long, value_name = "MS")]
+    pub debounce: Option<u64>,
+
+    /// Control socket of the window, see the control module [default: $XDG_RUNTIME_DIR/live_watch.sock]
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+
+    /// Open another window, instead of showing the paths in the one already open
+    #[arg(long)]
+    pub new_window: bool,
+
+    /// How changes on disk are noticed
+    #[arg(long, value_enum, default_value_t = Backend::Auto)]
+    pub backend: Backend,
+
+    /// Milliseconds between scans of the polling backend [default: 1000]
+    #[arg(long, value_name = "MS")]
+    pub poll_interval: Option<u64>,
+
+    /// Record the raw filesystem events to FILE (JSON Lines), to replay them later on
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Replay the events recorded in FILE against the first path, instead of watching it
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    pub replay: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn debounce(&self) -> Option<Duration> {
+        self.debounce.map(Duration::from_millis)
+    }
+
+    pub fn poll_interval(&self) -> Option<Duration> {
+        self.poll_interval.map(Duration::from_millis)
+    }
+}
+
+/// Parse the command line, exiting with a usage message (and a nonzero code) when it makes no sense
+///
+/// Paths come back absolute, so every path reported by the watcher can be traced back to one of them.
+pub fn parse() -> Args {
+    let mut args = Args::parse();
+    let before = args.paths.len();
+    args.paths.retain(|path| path.as_os_str() != "-");
+    args.stdin = args.paths.len() != before;
+    if args.paths.is_empty() && !args.stdin {
+        args.paths.push(PathBuf::from("."));
+    }
+
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for path in &args.paths {
+        let root = match path.canonicalize() {
+            Ok(root) => root,
+            Err(err) => usage_error(format!("can not watch '{}': {}", path.display(), err)),
+        };
+        // Files are listed by their path starting at the name of their root, so those names have to differ
+        let name = root.file_name();
+        if roots.iter().any(|other| other.file_name() == name) {
+            usage_error(format!(
+                "'{}' has the same name as another path, watched paths need different names",
+                path.display()
+            ));
         }
-    };
+        roots.push(root);
+    }
+    args.paths = roots;
+
+    for glob in &args.ignore_globs {
+        if let Err(err) = GitignoreBuilder::new(".").add_line(None, glob) {
+            usage_error(format!("bad --ignore glob '{}': {}", glob, err));
+        }
+    }
+
+    args
+}
 
-    result
+fn usage_error(msg: String) -> ! {
+    Args::command()
+        .error(ErrorKind::ValueValidation, msg)
+        .exit()
 }
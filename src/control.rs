@@ -0,0 +1,280 @@
+//! Driving a running window from scripts, over a Unix socket speaking JSON Lines
+//!
+//! Every line sent is one request, answered by one line:
+//!
+//! ```text
+//! {"cmd":"open","path":"/home/me/notes.md"}      a file below the roots is selected, anything else is switched to
+//! {"cmd":"switch_root","path":"/home/me/project"}
+//! {"cmd":"select","key":"project/src/main.rs"}   a key of the Watch List
+//! {"cmd":"set_text_mode","mode":"lines"}         what --text-mode takes
+//! {"cmd":"current"}                              {"ok":true,"key":"project/src/main.rs","path":"/home/me/project/src/main.rs","text_mode":"lines"}
+//! {"cmd":"subscribe"}                            {"ok":true}, then every change as the JSON `--json` prints
+//! ```
+//!
+//! Failures are answered with `{"ok":false,"error":"…"}`. A second `live_watch <path>` opens its paths through the
+//! socket of the first, instead of opening another window.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{broadcast, oneshot};
+
+use crate::json_events::JsonEvent;
+use crate::MagicError;
+
+/// How many events a subscriber may fall behind before it misses some
+const EVENT_BACKLOG: usize = 256;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    Open { path: PathBuf },
+    SwitchRoot { path: PathBuf },
+    Select { key: PathBuf },
+    SetTextMode { mode: String },
+    Current,
+    Subscribe,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct Reply {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_mode: Option<String>,
+}
+
+impl Reply {
+    pub fn ok() -> Reply {
+        Reply {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn err(msg: impl ToString) -> Reply {
+        Reply {
+            error: Some(msg.to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A request waiting for the window to answer it
+pub struct Pending {
+    request: Request,
+    reply_tx: oneshot::Sender<Reply>,
+}
+
+impl Pending {
+    pub fn request_ref(&self) -> &Request {
+        &self.request
+    }
+
+    pub fn reply(self, reply: Reply) {
+        // The script may have hung up in the meantime
+        let _ = self.reply_tx.send(reply);
+    }
+}
+
+/// `$XDG_RUNTIME_DIR/live_watch.sock`, or a socket of the user's own in the temporary directory
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => Path::new(&runtime_dir).join("live_watch.sock"),
+        None => {
+            // SAFETY: getuid can not fail
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("live_watch-{}.sock", uid))
+        }
+    }
+}
+
+/// Fail when the file at `path` (if there is one) belongs to another user
+///
+/// Anyone can put a socket in the temporary directory first, to be sent the paths meant for a window of ours.
+fn check_owner(path: &Path) -> std::io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        metadata => metadata?,
+    };
+    // SAFETY: getuid can not fail
+    let uid = unsafe { libc::getuid() };
+    if metadata.uid() != uid {
+        let msg = format!("{} belongs to another user", path.display());
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            msg,
+        ));
+    }
+    Ok(())
+}
+
+/// Send `requests` to the window listening on `socket`, one after the other, returns its replies
+///
+/// Fails when no window listens there, or the socket is not the user's own.
+pub fn send(socket: &Path, requests: &[Request]) -> Result<Vec<Reply>, MagicError> {
+    check_owner(socket)?;
+    let mut stream = UnixStream::connect(socket)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut replies = Vec::new();
+    for request in requests {
+        writeln!(stream, "{}", serde_json::to_string(request)?)?;
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err("the window hung up".into());
+        }
+        replies.push(serde_json::from_str(&line)?);
+    }
+
+    Ok(replies)
+}
+
+/// The listening end of the socket, requests are answered by whoever holds it
+pub struct Control {
+    path: PathBuf,
+    request_rx: UnboundedReceiver<Pending>,
+    event_tx: broadcast::Sender<JsonEvent>,
+}
+
+impl Control {
+    /// Listen on `path`, taking over a socket file of the user's that nobody answers on anymore
+    pub fn bind(path: &Path, rt: Arc<Mutex<tokio::runtime::Runtime>>) -> std::io::Result<Control> {
+        let listener = match std::os::unix::net::UnixListener::bind(path) {
+            Err(err) if err.kind() == std::io::ErrorKind::AddrInUse => {
+                check_owner(path)?;
+                // Another window is still using it
+                if UnixStream::connect(path).is_ok() {
+                    return Err(err);
+                }
+                std::fs::remove_file(path)?;
+                std::os::unix::net::UnixListener::bind(path)?
+            }
+            listener => listener?,
+        };
+        listener.set_nonblocking(true)?;
+
+        let (request_tx, request_rx) = unbounded_channel();
+        let (event_tx, _) = broadcast::channel(EVENT_BACKLOG);
+        let accepting = accept(listener, request_tx, event_tx.clone());
+        crate::force_am_once(rt, |rt| {
+            rt.spawn(accepting);
+        });
+
+        Ok(Control {
+            path: path.to_path_buf(),
+            request_rx,
+            event_tx,
+        })
+    }
+
+    pub fn path_ref(&self) -> &Path {
+        &self.path
+    }
+
+    /// The next request to answer, if one is waiting
+    pub fn try_next(&mut self) -> Option<Pending> {
+        self.request_rx.try_recv().ok()
+    }
+
+    /// Send an event to every subscriber
+    pub fn publish(&self, event: JsonEvent) {
+        // Nobody subscribed is fine
+        let _ = self.event_tx.send(event);
+    }
+}
+
+impl Drop for Control {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+async fn accept(
+    listener: std::os::unix::net::UnixListener,
+    request_tx: UnboundedSender<Pending>,
+    event_tx: broadcast::Sender<JsonEvent>,
+) {
+    let Ok(listener) = tokio::net::UnixListener::from_std(listener) else {
+        return;
+    };
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(connection(stream, request_tx.clone(), event_tx.clone()));
+    }
+}
+
+async fn write_line(write: &mut OwnedWriteHalf, line: String) -> std::io::Result<()> {
+    write.write_all((line + "\n").as_bytes()).await
+}
+
+// The next event for a subscriber, never for a connection that did not subscribe
+async fn next_event(events: &mut Option<broadcast::Receiver<JsonEvent>>) -> JsonEvent {
+    let Some(event_rx) = events else {
+        return futures::future::pending().await;
+    };
+    loop {
+        match event_rx.recv().await {
+            Ok(event) => return event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return futures::future::pending().await,
+        }
+    }
+}
+
+/// Answer the requests of one script until it hangs up
+async fn connection(
+    stream: tokio::net::UnixStream,
+    request_tx: UnboundedSender<Pending>,
+    event_tx: broadcast::Sender<JsonEvent>,
+) -> std::io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(read).lines();
+    let mut events = None;
+
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => line?,
+            event = next_event(&mut events) => {
+                write_line(&mut write, event.to_line()).await?;
+                continue;
+            }
+        };
+        let Some(line) = line else {
+            return Ok(());
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let reply = match serde_json::from_str(&line) {
+            Err(err) => Reply::err(format!("bad request: {}", err)),
+            Ok(Request::Subscribe) => {
+                events = Some(event_tx.subscribe());
+                Reply::ok()
+            }
+            Ok(request) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                if request_tx.send(Pending { request, reply_tx }).is_err() {
+                    return Ok(());
+                }
+                reply_rx
+                    .await
+                    .unwrap_or_else(|_| Reply::err("the window is closing"))
+            }
+        };
+        // Only strings and bools, which always serialize
+        write_line(&mut write, serde_json::to_string(&reply).unwrap()).await?;
+    }
+}
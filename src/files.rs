@@ -1,22 +1,15 @@
-use crate::{
-    cmd_args,
-    eframe_tools::{self, make_rich, ModalMachine},
-    files, font_size_default,
-    live_watch::watcher_keep::{self, WatcherUpdate},
-    MagicError,
-};
+use crate::{watching::watcher_keep::WatcherUpdate, MagicError};
 use std::{
     collections::{BTreeMap, HashMap},
+    ops::Range,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    sync::Arc,
 };
 
-use egui::RichText;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::sync::mpsc::Sender;
 use walkdir::WalkDir;
 
-use std::task::Context;
-use std::task::Poll;
-
 pub struct MasterPath {
     path_buf: Option<PathBuf>,
     pub path_tx: tokio::sync::mpsc::Sender<PathBuf>,
@@ -40,49 +33,114 @@ impl MasterPath {
     pub fn new(path_buf: Option<PathBuf>, path_tx: Sender<PathBuf>) -> MasterPath {
         MasterPath { path_buf, path_tx }
     }
+
+    /// Watch `path` instead, whatever lists the files follows with [`FileStore::switch_roots`]
+    pub fn switch(&mut self, path: PathBuf) -> Result<(), MagicError> {
+        self.path_tx.try_send(path.clone())?;
+        self.path_buf = Some(path);
+        Ok(())
+    }
 }
 
-type Standard = Vec<RichText>;
-type LineSeparation = Vec<RichText>;
-type AllLineSeparation = Vec<RichText>;
+/// Byte range into the text of a [`FileForm`], one range per piece of text shown to the user
+pub type Segment = Range<usize>;
+
+type Standard = Vec<Segment>;
+type LineSeparation = Vec<Segment>;
+type AllLineSeparation = Vec<Segment>;
 
 #[derive(Default)]
 pub struct FileForm {
+    text: String,
+    // Byte offset that each line of `text` starts at
+    line_starts: Vec<usize>,
     standard: Option<Standard>,
     line_separation: Option<LineSeparation>,
     all_line_separation: Option<AllLineSeparation>,
 }
 
 impl FileForm {
-    fn get_standard_mut(&mut self) -> &mut Option<Standard> {
-        &mut self.standard
+    fn get_text_ref(&self) -> &String {
+        &self.text
     }
+
+    fn get_line_starts_ref(&self) -> &Vec<usize> {
+        &self.line_starts
+    }
+
     fn get_standard_ref(&self) -> &Option<Standard> {
         &self.standard
     }
 
-    fn get_line_separation_mut(&mut self) -> &mut Option<LineSeparation> {
-        &mut self.line_separation
-    }
     fn get_line_separation_ref(&self) -> &Option<LineSeparation> {
         &self.line_separation
     }
 
-    fn get_all_line_separation_mut(&mut self) -> &mut Option<AllLineSeparation> {
-        &mut self.all_line_separation
-    }
     fn get_all_line_separation_ref(&self) -> &Option<AllLineSeparation> {
         &self.all_line_separation
     }
 }
 
-use crate::live_watch::settings::TextMode;
+use crate::cmd_args::TextModeArg;
+use crate::ignore_rules::IgnoreRules;
+use crate::list_filters::{ListFilters, SizeLimit};
+
+/// How the text of a file is split up for showing
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum TextMode {
+    Newline,
+    AllNewline,
+    Selectable,
+    Standard,
+}
+
+impl From<TextModeArg> for TextMode {
+    fn from(text_mode: TextModeArg) -> Self {
+        match text_mode {
+            TextModeArg::Standard => TextMode::Standard,
+            TextModeArg::Lines => TextMode::Newline,
+            TextModeArg::AllLines => TextMode::AllNewline,
+            TextModeArg::Selectable => TextMode::Selectable,
+        }
+    }
+}
+
+impl From<TextMode> for TextModeArg {
+    fn from(text_mode: TextMode) -> Self {
+        match text_mode {
+            TextMode::Standard => TextModeArg::Standard,
+            TextMode::Newline => TextModeArg::Lines,
+            TextMode::AllNewline => TextModeArg::AllLines,
+            TextMode::Selectable => TextModeArg::Selectable,
+        }
+    }
+}
+
 impl FileForm {
     pub fn line_separation_ref(&self) -> &Option<LineSeparation> {
         &self.line_separation
     }
 
-    pub fn get_file_text(&self, text_mode: &TextMode) -> Option<&Vec<RichText>> {
+    pub fn text_ref(&self) -> &str {
+        self.get_text_ref()
+    }
+
+    pub fn segment_text(&self, segment: &Segment) -> &str {
+        &self.get_text_ref()[segment.clone()]
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.get_line_starts_ref().len()
+    }
+
+    /// The (1 based) line number that the byte at `offset` sits on
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.get_line_starts_ref()
+            .partition_point(|start| *start <= offset)
+            .max(1)
+    }
+
+    pub fn get_file_text(&self, text_mode: &TextMode) -> Option<&Vec<Segment>> {
         match text_mode {
             TextMode::Newline => self.get_line_separation_ref(),
             TextMode::AllNewline => self.get_all_line_separation_ref(),
@@ -93,15 +151,18 @@ impl FileForm {
     }
 }
 
-// fn should_separate(chr: &char) -> bool {
-//     if *chr != ' ' || *chr != '\n' {
-//         return true;
-//     }
-//     false
-// }
+// Lines that hold nothing but their newline
+fn is_blank_line(line: &str) -> bool {
+    matches!(line, "\n" | "\r\n")
+}
 
-fn do_seperation(buffer: Vec<char>) -> String {
-    buffer.into_iter().collect::<String>()
+/// Every line of `text` including its trailing newline
+fn line_segments(text: &str) -> impl Iterator<Item = Segment> + '_ {
+    text.split_inclusive('\n').scan(0, |start, line| {
+        let segment = *start..*start + line.len();
+        *start = segment.end;
+        Some(segment)
+    })
 }
 
 impl FileForm {
@@ -111,67 +172,36 @@ impl FileForm {
         all_line_separation: bool,
         file_string: String,
     ) -> FileForm {
-        // let standard = standard.and_then(|s| {
         let standard = if standard {
-            let s = file_string.clone();
-            let rt = eframe_tools::make_rich(s, font_size_default());
-            Some(vec![rt])
+            let whole: Segment = 0..file_string.len();
+            Some(vec![whole])
         } else {
             None
         };
 
-        // let line_separation = line_separation.and_then(|s| {
+        // lines that hold nothing but their newline (\n or \r\n) are dropped
         let line_separation = if line_separation {
-            let s = file_string.clone();
-            let mut rich_sep = Vec::new();
-            let mut buffer = Vec::new();
-            let mut track = false;
-            let len = s.chars().count() - 1;
-            for (index, chr) in s.chars().enumerate() {
-                buffer.push(chr);
-
-                // we must remember whether there is only space/newline chars in the buffer
-                if chr != '\n' && !track {
-                    track = true
-                }
-
-                if chr == '\n' || index == len {
-                    if track {
-                        let text = do_seperation(buffer);
-                        let rich_text = make_rich(text, font_size_default());
-                        rich_sep.push(rich_text);
-                    }
-                    buffer = Vec::new();
-                    track = false;
-                }
-            }
-
-            Some(rich_sep)
+            let separated = line_segments(&file_string)
+                .filter(|segment| !is_blank_line(&file_string[segment.clone()]))
+                .collect();
+            Some(separated)
         } else {
             None
         };
 
-        // let all_line_separation = all_line_separation.and_then(|s| {
         let all_line_separation = if all_line_separation {
-            let s = file_string;
-            let mut rich_sep = Vec::new();
-            let mut buffer = Vec::new();
-            let len = s.chars().count() - 1;
-            for (index, chr) in s.chars().enumerate() {
-                buffer.push(chr);
-                if chr == '\n' || index == len {
-                    let text = do_seperation(buffer);
-                    let rich_text = make_rich(text, font_size_default());
-                    rich_sep.push(rich_text);
-                    buffer = Vec::new();
-                }
-            }
-            Some(rich_sep)
+            Some(line_segments(&file_string).collect())
         } else {
             None
         };
 
+        let line_starts = line_segments(&file_string)
+            .map(|segment| segment.start)
+            .collect();
+
         FileForm {
+            text: file_string,
+            line_starts,
             standard,
             line_separation,
             all_line_separation,
@@ -179,9 +209,44 @@ impl FileForm {
     }
 }
 
+impl FileForm {
+    /// Add `text` to the end, only the last line (which `text` may go on with) is split up again
+    fn append(&mut self, text: &str) {
+        let from = self.line_starts.last().copied().unwrap_or(0);
+        self.text.push_str(text);
+        let tail: Vec<Segment> = line_segments(&self.text[from..])
+            .map(|segment| segment.start + from..segment.end + from)
+            .collect();
+
+        self.line_starts.pop();
+        self.line_starts
+            .extend(tail.iter().map(|segment| segment.start));
+        if let Some(standard) = &mut self.standard {
+            let whole: Segment = 0..self.text.len();
+            *standard = vec![whole];
+        }
+        if let Some(line_separation) = &mut self.line_separation {
+            line_separation.retain(|segment| segment.start < from);
+            line_separation.extend(
+                tail.iter()
+                    .filter(|segment| !is_blank_line(&self.text[(*segment).clone()]))
+                    .cloned(),
+            );
+        }
+        if let Some(all_line_separation) = &mut self.all_line_separation {
+            all_line_separation.retain(|segment| segment.start < from);
+            all_line_separation.extend(tail);
+        }
+    }
+}
+
+// Every load of a file gets a new version, so anything derived from its text knows when to recompute
+static NEXT_FILE_VERSION: AtomicU64 = AtomicU64::new(1);
+
 pub struct File {
     file: FileForm,
     path: PathBuf,
+    version: u64,
 }
 
 impl File {
@@ -201,6 +266,10 @@ impl File {
     fn get_path_mut(&mut self) -> &mut PathBuf {
         &mut self.path
     }
+
+    fn get_version_ref(&self) -> &u64 {
+        &self.version
+    }
 }
 
 impl File {
@@ -208,6 +277,7 @@ impl File {
         File {
             file: FileForm::default(),
             path,
+            version: 0,
         }
     }
 
@@ -221,13 +291,37 @@ impl File {
         let file = File {
             file: file_form,
             path: path_buf.clone(),
+            version: NEXT_FILE_VERSION.fetch_add(1, Ordering::Relaxed),
         };
 
         Ok(file)
     }
 
-    fn file_ref(&self) -> &FileForm {
-        &self.get_file_ref()
+    /// A file that is not on disk, holding `text`
+    pub fn from_text(path: PathBuf, text: String) -> File {
+        File {
+            file: FileForm::new(true, true, true, text),
+            path,
+            version: NEXT_FILE_VERSION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Add `text` to the end of the file, as a new version
+    pub fn append(&mut self, text: &str) {
+        self.get_file_mut().append(text);
+        self.version = NEXT_FILE_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn path_ref(&self) -> &PathBuf {
+        self.get_path_ref()
+    }
+
+    pub fn file_form_ref(&self) -> &FileForm {
+        self.get_file_ref()
+    }
+
+    pub fn version(&self) -> u64 {
+        *self.get_version_ref()
     }
 
     // fn reload(&mut self) -> Result<(), MagicError> {
@@ -238,19 +332,39 @@ impl File {
 }
 
 type CachedFiles = HashMap<PathBuf, Option<File>>;
+
+/// The key of what is piped in when `-` is watched, listed next to the roots and never filtered out
+pub const STDIN_KEY: &str = "<stdin>";
+
+fn is_stdin(key: &Path) -> bool {
+    key == Path::new(STDIN_KEY)
+}
+
+/// The part of `key` inside its root, what globs are matched against (like trigger globs, `src/*.rs`)
+///
+/// A file watched on its own is matched by its name.
+pub fn inside_root(key: &Path) -> &Path {
+    let mut components = key.components();
+    components.next();
+    match components.as_path() {
+        inside if inside.as_os_str().is_empty() => key,
+        inside => inside,
+    }
+}
+
 pub struct FileCache {
-    current_file: PathBuf,
+    // Every watched file or directory, each key starts with the name of one of them
+    roots: Vec<PathBuf>,
     cached_files: CachedFiles,
     allow_caching: bool,
+    // Bumped whenever a file is added, removed or renamed
+    listing_version: u64,
 }
 
 // Private
 impl FileCache {
-    fn get_current_file_ref(&self) -> &PathBuf {
-        &self.current_file
-    }
-    fn get_current_file_mut(&mut self) -> &mut PathBuf {
-        &mut self.current_file
+    fn get_roots_ref(&self) -> &Vec<PathBuf> {
+        &self.roots
     }
     fn get_cached_files_ref(&self) -> &CachedFiles {
         &self.cached_files
@@ -260,94 +374,335 @@ impl FileCache {
     }
 }
 
-// Setters
+// Public
 impl FileCache {
-    pub fn current_file_set(&mut self, new_current_file: PathBuf) {
-        *self.get_current_file_mut() = new_current_file;
+    /// Every file that is loaded into the cache, keyed the same way as the Watch List
+    pub fn files(&self) -> impl Iterator<Item = (&PathBuf, &File)> {
+        self.get_cached_files_ref()
+            .iter()
+            .filter_map(|(path, file)| Some((path, file.as_ref()?)))
     }
-}
 
-// Public
-impl FileCache {
-    pub fn current_file_ref(&self) -> &PathBuf {
-        &self.get_current_file_ref()
+    /// Every key of the cache (loaded or not), the relative paths shown to the user
+    pub fn keys(&self) -> impl Iterator<Item = &PathBuf> {
+        self.get_cached_files_ref().keys()
     }
 
-    pub fn current_file_mut(&mut self) -> &mut PathBuf {
-        self.get_current_file_mut()
+    pub fn listing_version(&self) -> u64 {
+        self.listing_version
     }
 
-    pub fn full_file(&self) -> Option<&FileForm> {
-        // println!("Current Path: <{:?}>", self.get_current_file_ref());
-        let mut b = Vec::new();
-        self.get_cached_files_ref()
+    pub fn contains(&self, key: &Path) -> bool {
+        self.get_cached_files_ref().contains_key(key)
+    }
+
+    pub fn file_ref(&self, key: &Path) -> Option<&File> {
+        self.get_cached_files_ref().get(key)?.as_ref()
+    }
+
+    pub fn roots_ref(&self) -> &Vec<PathBuf> {
+        self.get_roots_ref()
+    }
+
+    /// The root `key` lives in, found by the name its key starts with
+    fn root_of(&self, key: &Path) -> Option<&PathBuf> {
+        let name = key.components().next()?.as_os_str();
+        self.get_roots_ref()
+            .iter()
+            .find(|root| root.file_name() == Some(name))
+    }
+
+    /// Where on disk the file behind `key` lives
+    pub fn absolute_path(&self, key: &Path) -> PathBuf {
+        match self.root_of(key).and_then(|root| root.parent()) {
+            Some(parent) => parent.join(key),
+            None => key.to_path_buf(),
+        }
+    }
+
+    /// The key `path` (as the watcher reports it) has, whether or not it is in the cache
+    pub fn relative_key(&self, path: &Path) -> Option<PathBuf> {
+        if is_stdin(path) {
+            return Some(path.to_path_buf());
+        }
+        let key = |path: &Path| {
+            let root = self
+                .get_roots_ref()
+                .iter()
+                .find(|root| path.starts_with(root))?;
+            let parent = root.parent().unwrap_or(root);
+            path.strip_prefix(parent).ok().map(Path::to_path_buf)
+        };
+        if let Some(key) = key(path) {
+            return Some(key);
+        }
+        // Relative (or otherwise not canonical) paths, only works while the path still exists
+        key(&Path::canonicalize(path).ok()?)
+    }
+
+    /// Insert or replace a file, returns true if it was not in the cache before
+    pub fn insert(&mut self, key: PathBuf, file: Option<File>) -> bool {
+        let is_new = self.get_cached_files_mut().insert(key, file).is_none();
+        if is_new {
+            self.listing_version += 1;
+        }
+        is_new
+    }
+
+    /// Remove `key` and, if it is a directory, everything below it, returns false if there was nothing to remove
+    pub fn remove(&mut self, key: &Path) -> bool {
+        let before = self.get_cached_files_ref().len();
+        self.get_cached_files_mut()
+            .retain(|path, _| !path.starts_with(key));
+        let removed = self.get_cached_files_ref().len() != before;
+        if removed {
+            self.listing_version += 1;
+        }
+        removed
+    }
+
+    /// Move `from` (a file, or a directory and everything below it) to `to`, returns the keys that moved
+    pub fn rename(&mut self, from: &Path, to: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let moved: Vec<PathBuf> = self
             .keys()
-            .for_each(|s| b.push(s.display()));
-        let cached_files = self.get_cached_files_ref();
-        // println!("cached files");
-        let file = cached_files.get(self.get_current_file_ref())?.as_ref();
-        // println!("files: <{}>", self.current_file.display());
-        let file = file?;
-        Some(file.file_ref())
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        moved
+            .into_iter()
+            .map(|old_key| {
+                // Joining the empty path of the renamed file itself would add a trailing slash
+                let new_key = match old_key.strip_prefix(from).unwrap() {
+                    inside if inside.as_os_str().is_empty() => to.to_path_buf(),
+                    inside => to.join(inside),
+                };
+                let file = self.get_cached_files_mut().remove(&old_key).flatten();
+                self.get_cached_files_mut().insert(new_key.clone(), file);
+                self.listing_version += 1;
+                (old_key, new_key)
+            })
+            .collect()
+    }
+
+    /// Forget every file (but what is piped in), then list the ones below `roots` instead
+    pub fn switch_roots(
+        &mut self,
+        roots: &[PathBuf],
+        dir_list: BTreeMap<PathBuf, ()>,
+        filters: &ListFilters,
+    ) {
+        self.roots = canonical_roots(roots);
+        self.get_cached_files_mut().retain(|key, _| is_stdin(key));
+        // Keys of the new roots may be the same as the old ones, the files behind them are not
+        self.listing_version += 1;
+        self.relist(dir_list, filters);
+    }
+
+    /// Make the cache hold exactly the files of `dir_list`
+    ///
+    /// Files no longer listed are dropped, files listed for the first time are loaded (if the filters allow it). Files
+    /// already loaded stay loaded, whatever their size. What is piped in stays too.
+    pub fn relist(&mut self, dir_list: BTreeMap<PathBuf, ()>, filters: &ListFilters) {
+        let before = self.get_cached_files_ref().len();
+        self.get_cached_files_mut()
+            .retain(|key, _| dir_list.contains_key(key) || is_stdin(key));
+        let mut changed = self.get_cached_files_ref().len() != before;
+
+        for key in dir_list.into_keys() {
+            if !self.contains(&key) {
+                let file = load_listed_file(&self.absolute_path(&key), filters);
+                self.get_cached_files_mut().insert(key, file);
+                changed = true;
+            }
+        }
+        if changed {
+            self.listing_version += 1;
+        }
+    }
+
+    /// Load a listed file that is not loaded yet (left out for its size), whatever the filters say
+    pub fn load(&mut self, key: &Path) -> Result<(), MagicError> {
+        if !self.contains(key) || self.file_ref(key).is_some() {
+            return Ok(());
+        }
+        let file = File::load_file(&self.absolute_path(key))?;
+        self.get_cached_files_mut()
+            .insert(key.to_path_buf(), Some(file));
+
+        Ok(())
+    }
+
+    /// Bring the cache in line with one update from the watcher, returns what changed by key
+    ///
+    /// Updates outside every root, or for files the filters do not list, change nothing.
+    pub fn apply(
+        &mut self,
+        watcher_update: WatcherUpdate,
+        filters: &ListFilters,
+    ) -> Vec<CacheUpdate> {
+        let mut updates = Vec::new();
+        match watcher_update {
+            WatcherUpdate::FileContent(updated_file) => {
+                let Some(key) = self.relative_key(updated_file.get_path_ref()) else {
+                    return updates;
+                };
+                let piped = is_stdin(&key);
+                if !filters.lists(&key) && !piped {
+                    return updates;
+                }
+                // Too big to load unopened, unless it was opened (loaded) already
+                let size = updated_file.file_form_ref().text_ref().len() as u64;
+                let loaded = self.file_ref(&key).is_some();
+                let updated_file = match filters.loads(size) || loaded || piped {
+                    true => Some(updated_file),
+                    false => None,
+                };
+                self.insert(key.clone(), updated_file);
+                updates.push(CacheUpdate::Changed(key));
+            }
+
+            WatcherUpdate::FileAppend(path, text) => {
+                let Some(key) = self.relative_key(&path) else {
+                    return updates;
+                };
+                if !filters.lists(&key) && !is_stdin(&key) {
+                    return updates;
+                }
+                match self.get_cached_files_mut().get_mut(&key) {
+                    Some(Some(file)) => file.append(&text),
+                    _ => {
+                        self.insert(key.clone(), Some(File::from_text(path, text)));
+                    }
+                }
+                updates.push(CacheUpdate::Changed(key));
+            }
+
+            WatcherUpdate::FileTooBig(path) => {
+                let Some(key) = self.relative_key(&path) else {
+                    return updates;
+                };
+                if !filters.lists(&key) {
+                    return updates;
+                }
+                // Opened files are read whatever their size, the watcher left that to the cache
+                let updated_file = match self.file_ref(&key) {
+                    Some(_) => File::load_file(&path).ok(),
+                    None => None,
+                };
+                self.insert(key.clone(), updated_file);
+                updates.push(CacheUpdate::Changed(key));
+            }
+
+            WatcherUpdate::FileRename(rename_event) => {
+                let (from, to) = rename_event.from_and_to_ref();
+                let (Some(from), Some(to)) = (self.relative_key(from), self.relative_key(to))
+                else {
+                    return updates;
+                };
+                for (from, to) in self.rename(&from, &to) {
+                    // Moving out of what the filters list is as good as a delete
+                    match filters.lists(&to) {
+                        true => updates.push(CacheUpdate::Renamed(from, to)),
+                        false => {
+                            self.remove(&to);
+                            updates.push(CacheUpdate::Deleted(from));
+                        }
+                    }
+                }
+            }
+
+            WatcherUpdate::FileDelete(deleted_path) => {
+                let Some(deleted) = self.relative_key(&deleted_path) else {
+                    return updates;
+                };
+                // Deleted twice (as part of a directory, or by events overlapping) is only reported once
+                if self.remove(&deleted) {
+                    updates.push(CacheUpdate::Deleted(deleted));
+                }
+            }
+        }
+
+        updates
     }
 
     pub fn new(
-        current_path: impl Into<PathBuf>,
-        first_filepath: impl Into<PathBuf>,
+        roots: &[PathBuf],
         dir_list: BTreeMap<PathBuf, ()>,
+        filters: &ListFilters,
     ) -> FileCache {
-        // let current_file = File::new(current_path.into());
-        let current_file = current_path.into();
-        let dir_list = dir_list.into_keys().collect();
-        let cached_files = load_dir_files(dir_list);
+        let roots = canonical_roots(roots);
 
         let allow_caching = true;
-        FileCache {
-            current_file: first_filepath.into(),
-            cached_files,
+        let mut file_cache = FileCache {
+            roots,
+            cached_files: HashMap::new(),
             allow_caching,
-        }
+            listing_version: 0,
+        };
+        file_cache.relist(dir_list, filters);
+        file_cache
     }
 }
 
-pub struct WatchList {
-    mm: ModalMachine,
+fn canonical_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .map(|root| Path::canonicalize(root).unwrap_or(root.to_path_buf()))
+        .collect()
+}
+
+/// What [`FileStore::apply`] changed in the cache, by key
+#[derive(Clone, Debug)]
+pub enum CacheUpdate {
+    Changed(PathBuf),
+    Renamed(PathBuf, PathBuf),
+    Deleted(PathBuf),
+}
+
+/// The files below the watched roots, kept up to date by applying the updates of a
+/// [`Watcher`](crate::watching::watcher::Watcher)
+///
+/// Only files the ignore rules and the filters let through are listed. Listed files are loaded right away unless the
+/// filters say they are too big, those load on [`FileCache::load`].
+pub struct FileStore {
     file_cache: FileCache,
-    file_update_rx: Receiver<watcher_keep::WatcherUpdate>,
+    ignore_rules: Arc<IgnoreRules>,
+    filters: ListFilters,
+    size_limit: SizeLimit,
 }
 
 // Private
-impl WatchList {
+impl FileStore {
     fn get_file_cache_ref(&self) -> &FileCache {
         &self.file_cache
     }
     fn get_file_cache_mut(&mut self) -> &mut FileCache {
         &mut self.file_cache
     }
-
-    fn get_modal_machine_mut(&mut self) -> &mut ModalMachine {
-        &mut self.mm
-    }
-
-    fn get_modal_machine_ref(&self) -> &ModalMachine {
-        &self.mm
-    }
-
-    fn get_file_update_rx_mut(&mut self) -> &mut Receiver<watcher_keep::WatcherUpdate> {
-        &mut self.file_update_rx
-    }
-
-    fn get_file_update_rx_ref(&self) -> &Receiver<watcher_keep::WatcherUpdate> {
-        &self.file_update_rx
+    fn get_filters_ref(&self) -> &ListFilters {
+        &self.filters
     }
 }
 
-impl WatchList {
-    pub fn modal_machine_mut(&mut self) -> &mut ModalMachine {
-        self.get_modal_machine_mut()
-    }
-    pub fn modal_machine_ref(&self) -> &ModalMachine {
-        &self.get_modal_machine_ref()
+// Public
+impl FileStore {
+    /// List (and load) the files below `roots`
+    pub fn new(
+        roots: &[PathBuf],
+        ignore_rules: Arc<IgnoreRules>,
+        filters: ListFilters,
+    ) -> FileStore {
+        let dir_list = make_roots_list(roots, &ignore_rules, &filters);
+        let file_cache = FileCache::new(roots, dir_list, &filters);
+
+        let size_limit = SizeLimit::new();
+        size_limit.set(filters.max_file_size());
+        FileStore {
+            file_cache,
+            ignore_rules,
+            filters,
+            size_limit,
+        }
     }
 
     pub fn file_cache_ref(&self) -> &FileCache {
@@ -358,131 +713,38 @@ impl WatchList {
         self.get_file_cache_mut()
     }
 
-    pub fn file_update_tx_ref(&self) -> &Receiver<watcher_keep::WatcherUpdate> {
-        self.get_file_update_rx_ref()
+    pub fn filters_ref(&self) -> &ListFilters {
+        self.get_filters_ref()
     }
-}
-
-// Public
-use crate::windows::error_messages::ErrorSender;
-impl WatchList {
-    pub fn new(
-        current_dir: &PathBuf,
-        name: impl Into<String>,
-        file_update_rx: Receiver<watcher_keep::WatcherUpdate>,
-        err_rx: ErrorSender,
-    ) -> WatchList {
-        let dir_list = make_dir_list(current_dir);
-        let mut first = PathBuf::from("");
-
-        for k in dir_list.keys() {
-            if k.is_file() {
-                first = k.clone()
-            }
-        }
-
-        let mm = ModalMachine::new(first.clone(), dir_list.clone(), name.into());
-        let file_cache = FileCache::new(current_dir, first, dir_list);
 
-        WatchList {
-            mm,
-            file_cache,
-            file_update_rx,
-        }
+    /// Swap in new filters, listing the tree again and loading whatever became visible
+    pub fn set_filters(&mut self, filters: ListFilters) {
+        self.size_limit.set(filters.max_file_size());
+        self.filters = filters;
+        let roots = self.get_file_cache_ref().roots_ref();
+        let dir_list = make_roots_list(roots, &self.ignore_rules, &self.filters);
+        self.file_cache.relist(dir_list, &self.filters);
     }
 
-    pub fn handle_updates(&mut self, master_path_pb: PathBuf) {
-        let waker = futures::task::noop_waker();
-        let mut cx = Context::from_waker(&waker);
-
-        while let Poll::Ready(op) = self.get_file_update_rx_mut().poll_recv(&mut cx) {
-            println!("Poll trigger");
-            match op {
-                Some(watcher_update) => {
-                    println!("Watcher Update Recieved");
-                    match watcher_update {
-                        WatcherUpdate::FileContent(updated_file) => {
-                            println!(
-                                "-------------------------- <{}> --------------------------",
-                                updated_file.get_path_ref().display()
-                            );
-                            // let pb = &Path::canonicalize(updated_file.get_path_ref()).unwrap();
-                            println!(
-                                "All Keys: <{:?}>",
-                                self.get_file_cache_mut()
-                                    .get_cached_files_ref()
-                                    .keys()
-                                    .collect::<Vec<&PathBuf>>(),
-                            );
-
-                            println!(
-                                "Good: master Path: <{}>, updated file path: <{}>",
-                                master_path_pb.display(),
-                                updated_file.get_path_ref().display()
-                            );
-
-                            let key = get_directory_specific_path(
-                                &master_path_pb,
-                                updated_file.get_path_ref(),
-                            )
-                            .unwrap();
-                            self.get_file_cache_mut()
-                                .get_cached_files_mut()
-                                .get_mut(&key)
-                                .and_then(|file| {
-                                    *file = Some(updated_file);
-                                    None::<Option<File>>
-                                });
-                        }
+    /// Keep `size_limit` in line with the filters from now on, the watcher leaves files over it unread
+    pub fn share_size_limit(&mut self, size_limit: SizeLimit) {
+        size_limit.set(self.get_filters_ref().max_file_size());
+        self.size_limit = size_limit;
+    }
 
-                        WatcherUpdate::FileRename(rename_event) => {
-                            println!(
-                                "Watcher Update FileRename: current master path: <{}>",
-                                master_path_pb.display()
-                            );
-                            let (from, to) = rename_event.from_and_to_ref();
-                            let from =
-                                files::get_directory_specific_path(&master_path_pb, from).unwrap();
-                            let to =
-                                files::get_directory_specific_path(&master_path_pb, to).unwrap();
-                            // let (from, to) = files::get_directory_specific_path(, )
-                            println!("New Keys: <{}> || <{}>", from.display(), to.display());
-                            println!(
-                                "All Keys: <{:?}>",
-                                self.get_file_cache_mut().get_cached_files_mut().keys()
-                            );
-                            if let Some(value) = self
-                                .get_file_cache_mut()
-                                .get_cached_files_mut()
-                                .remove(&from)
-                            {
-                                self.get_file_cache_mut()
-                                    .get_cached_files_mut()
-                                    .insert(to.to_owned(), value);
-
-                                let options = self
-                                    .get_file_cache_mut()
-                                    .get_cached_files_mut()
-                                    .iter()
-                                    .map(|(pb, _)| (pb.to_owned(), ()))
-                                    .collect();
-                                self.modal_machine_mut().replace_options(options);
-                                println!("--------------------------------------------------------------------------------------------------------------------------------------------");
-                            }
-                        }
+    /// List the files below other roots, reading their ignore files instead of the old ones
+    ///
+    /// Returns the broken lines of the new ignore files. The watcher is switched over through its `path_tx`.
+    pub fn switch_roots(&mut self, roots: &[PathBuf]) -> Vec<String> {
+        let errors = self.ignore_rules.switch_roots(roots);
+        let dir_list = make_roots_list(roots, &self.ignore_rules, &self.filters);
+        self.file_cache.switch_roots(roots, dir_list, &self.filters);
+        errors
+    }
 
-                        _ => {
-                            println!("nothing watch update");
-                        }
-                    }
-                }
-                None => {
-                    panic!("Error, handle updates some how broke");
-                }
-            } // .unwrap() = Some(updated_file);
-              // .as_deref_mut()
-              // .insert(updated_file.get_path_ref(), Some(updated_file));
-        }
+    /// Bring the store in line with one update from the watcher, returns what changed by key
+    pub fn apply(&mut self, watcher_update: WatcherUpdate) -> Vec<CacheUpdate> {
+        self.file_cache.apply(watcher_update, &self.filters)
     }
 }
 
@@ -500,42 +762,54 @@ pub fn get_directory_specific_path(base: &PathBuf, strip: &PathBuf) -> Result<Pa
     Ok(stripped)
 }
 
-pub fn make_dir_list(current_dir: &PathBuf) -> BTreeMap<PathBuf, ()> {
-    WalkDir::new(current_dir)
+/// Every file below `current_dir` the ignore rules and filters let through, keyed by its path relative to the parent
+/// of `current_dir`
+pub fn make_dir_list(
+    current_dir: &PathBuf,
+    ignore_rules: &IgnoreRules,
+    filters: &ListFilters,
+) -> BTreeMap<PathBuf, ()> {
+    let mut walk_dir = WalkDir::new(current_dir);
+    if let Some(max_depth) = filters.max_depth() {
+        walk_dir = walk_dir.max_depth(max_depth);
+    }
+    let hidden = |entry: &walkdir::DirEntry| {
+        entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.')
+    };
+
+    walk_dir
         .into_iter()
+        // Ignored (and hidden, unless shown) directories are not even entered
+        .filter_entry(|entry| {
+            !ignore_rules.is_ignored(entry.path(), entry.file_type().is_dir())
+                && (filters.show_hidden() || !hidden(entry))
+        })
         .filter_map(|e| e.ok())
-        .into_iter()
-        .map(|entry| (entry.path().to_path_buf(), ()))
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| get_directory_specific_path(current_dir, &entry.into_path()).ok())
+        .filter(|key| filters.lists(key))
+        .map(|key| (key, ()))
         .collect::<BTreeMap<PathBuf, ()>>()
-    // .map(|entry| (Path::canonicalize(&entry.path().to_path_buf()).unwrap(), ()))
-    // .map(|entry| (entry.path().to_path_buf(), ()))
 }
 
-fn load_dir_files(file_list: Vec<PathBuf>) -> CachedFiles {
-    let mut cached_files = HashMap::new();
-    file_list.into_iter().for_each(|s| {
-        let f = if s.is_file() {
-            println!(
-                "FileName: -------<{}>-------",
-                s.file_name().unwrap().to_str().unwrap()
-            );
-            Some(File::load_file(&s).unwrap())
-        } else {
-            None
-        };
-        cached_files.insert(s, f);
-    });
-
-    cached_files
+/// Every file below any of `roots` the ignore rules and filters let through
+pub fn make_roots_list(
+    roots: &[PathBuf],
+    ignore_rules: &IgnoreRules,
+    filters: &ListFilters,
+) -> BTreeMap<PathBuf, ()> {
+    roots
+        .iter()
+        .flat_map(|root| make_dir_list(root, ignore_rules, filters))
+        .collect()
 }
 
-pub fn get_master_path() -> Result<(MasterPath, Receiver<PathBuf>), MagicError> {
-    let maybe_args = cmd_args::get_arg()?;
-
-    let path_buf = maybe_args.and_then(|cmd_arg| Some(Path::new(&cmd_arg).to_path_buf()));
-
-    let (tx, rx) = channel(32);
-    let master_path = MasterPath::new(path_buf, tx);
-
-    Ok((master_path, rx))
+/// Load the file at `path`, unless the filters say it is too big to load before it is opened
+fn load_listed_file(path: &PathBuf, filters: &ListFilters) -> Option<File> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if !filters.loads(size) {
+        return None;
+    }
+    // Files that can not be shown (not utf8, unreadable) stay listed without content
+    File::load_file(path).ok()
 }
@@ -0,0 +1,227 @@
+//! Running without a window (over SSH, on a box without a display): changes are printed to stdout as they happen
+//!
+//! Either for people (a line per change followed by its diff) or, with `--json`, as [`JsonEvent`] lines. With
+//! `--serve` the same changes also go to browsers, see [`crate::server`].
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use crate::changes::{Change, ChangeKind};
+use crate::cmd_args::Args;
+use crate::files::{FileCache, FileStore};
+use crate::json_events::{Inline, JsonEvent};
+use crate::list_filters::ListFilters;
+use crate::server::{self, Server};
+use crate::string_tools::line_diff;
+use crate::watching::backend::{self, WatchBackend};
+use crate::watching::watcher::{WatchEvent, WatchOptions, Watcher};
+use crate::watching::watcher_keep::WatcherUpdate;
+use crate::MagicError;
+
+/// Diffs longer than this are cut short, a rewritten file would otherwise flood the terminal
+const MAX_DIFF_LINES: usize = 200;
+
+/// How changes are printed
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Output {
+    Text,
+    Json(Option<Inline>),
+}
+
+/// A line saying what happened, followed by the diff of changed files
+fn text_report(change: &Change, file_cache: &FileCache) -> String {
+    let time = chrono::Local::now().format("%H:%M:%S");
+    let key = change.key_ref().display();
+    match change.kind() {
+        ChangeKind::Created => format!("{} created {}\n", time, key),
+        ChangeKind::Changed => {
+            let mut report = format!("{} changed {}\n", time, key);
+            let text = file_cache
+                .file_ref(change.key_ref())
+                .map(|file| file.file_form_ref().text_ref());
+            // Nothing to diff when either side was not loaded
+            let previous = text.and_then(|text| change.previous_text(text));
+            if let (Some(previous), Some(text)) = (previous, text) {
+                report.push_str(&line_diff(previous, text, MAX_DIFF_LINES));
+            }
+            report
+        }
+        ChangeKind::Renamed => {
+            let from = change.from_ref().unwrap_or(Path::new(""));
+            format!("{} renamed {} -> {}\n", time, from.display(), key)
+        }
+        ChangeKind::Deleted => format!("{} deleted {}\n", time, key),
+    }
+}
+
+fn json_report(change: &Change, file_cache: &FileCache, inline: Option<Inline>) -> String {
+    JsonEvent::from_change(change, file_cache, inline).to_line() + "\n"
+}
+
+fn touches(update: &WatcherUpdate, path: &Path) -> bool {
+    match update {
+        WatcherUpdate::FileContent(file) => file.path_ref() == path,
+        WatcherUpdate::FileTooBig(changed) | WatcherUpdate::FileAppend(changed, _) => {
+            changed == path
+        }
+        WatcherUpdate::FileRename(rename_event) => {
+            let (from, to) = rename_event.from_and_to_ref();
+            from == path || to == path
+        }
+        WatcherUpdate::FileDelete(deleted) => path.starts_with(deleted),
+    }
+}
+
+/// Add `update` to `batch`, replacing new content for the same file that nothing happened to since
+///
+/// Saving often truncates a file before writing it, only the written content is worth reporting.
+fn coalesce(batch: &mut Vec<WatcherUpdate>, update: WatcherUpdate) {
+    let changed = match &update {
+        WatcherUpdate::FileContent(file) => Some(file.path_ref()),
+        WatcherUpdate::FileTooBig(path) => Some(path),
+        WatcherUpdate::FileRename(_)
+        | WatcherUpdate::FileDelete(_)
+        | WatcherUpdate::FileAppend(..) => None,
+    };
+    if let Some(changed) = changed {
+        let last = batch.iter().rposition(|earlier| touches(earlier, changed));
+        if let Some(last) = last.filter(|last| {
+            matches!(
+                batch[*last],
+                WatcherUpdate::FileContent(_) | WatcherUpdate::FileTooBig(_)
+            )
+        }) {
+            batch.remove(last);
+        }
+    }
+    batch.push(update);
+}
+
+/// Wait for an update, then collect more until none arrived for `quiet`, None once the watcher is gone
+async fn next_batch(watcher: &mut Watcher, quiet: Duration) -> Option<Vec<WatcherUpdate>> {
+    let mut batch = Vec::new();
+    loop {
+        let event = match batch.is_empty() {
+            true => watcher.next().await?,
+            false => match tokio::time::timeout(quiet, watcher.next()).await {
+                Ok(Some(event)) => event,
+                _ => return Some(batch),
+            },
+        };
+        match event {
+            WatchEvent::Update(update) => coalesce(&mut batch, update),
+            // stdout is only for changes, so it stays parseable
+            WatchEvent::Error(loglet) => {
+                eprintln!("live_watch: {}: {}", loglet.kind_ref(), loglet.msg_ref())
+            }
+            // Nothing shows the activity feed or runs triggers without a window
+            WatchEvent::Activity(_) | WatchEvent::Changes(_) => {}
+        }
+    }
+}
+
+async fn report_changes(
+    mut watcher: Watcher,
+    store: Arc<RwLock<FileStore>>,
+    quiet: Duration,
+    output: Option<Output>,
+    server: Option<Arc<Server>>,
+) -> std::io::Result<()> {
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let batch = tokio::select! {
+            batch = next_batch(&mut watcher, quiet) => batch,
+            _ = &mut ctrl_c => return Ok(()),
+        };
+        let Some(batch) = batch else {
+            return Ok(());
+        };
+
+        let mut report = String::new();
+        for update in batch {
+            let mut store = store.write().unwrap();
+            for change in Change::apply(&mut store, update) {
+                let file_cache = store.file_cache_ref();
+                if let Some(server) = &server {
+                    server.publish(JsonEvent::from_change(&change, file_cache, None));
+                }
+                match output {
+                    Some(Output::Text) => report.push_str(&text_report(&change, file_cache)),
+                    Some(Output::Json(inline)) => {
+                        report.push_str(&json_report(&change, file_cache, inline))
+                    }
+                    None => {}
+                }
+            }
+        }
+        // A closed stdout (piped into `head`) ends the run
+        let mut stdout = std::io::stdout();
+        stdout.write_all(report.as_bytes())?;
+        stdout.flush()?;
+    }
+}
+
+/// Watch the (absolute) paths of `args` until Ctrl-C, printing every change to stdout and serving them with `--serve`
+///
+/// Uses the same [`Watcher`] and [`FileStore`] as the window, without starting one. Updates are collected
+/// until the tree was quiet for `--debounce`, so one save is one change.
+pub fn run(args: Args) -> Result<(), MagicError> {
+    let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new()?));
+    let roots: Vec<PathBuf> = args.paths.clone();
+    // Only serving prints nothing, unless asked to
+    let output = if args.json {
+        Some(Output::Json(args.json_inline.map(Inline::from)))
+    } else if args.headless || args.serve.is_none() {
+        Some(Output::Text)
+    } else {
+        None
+    };
+    // Bound before anything starts, so a taken port ends the run right away
+    let listener = match args.serve {
+        Some(addr) => Some(std::net::TcpListener::bind(addr)?),
+        None => None,
+    };
+
+    let mut options = WatchOptions::new();
+    options.ignore_globs = args.ignore_globs.clone();
+    options.debounce = args.debounce().unwrap_or(options.debounce);
+    options.backend = WatchBackend::from(args.backend);
+    options.poll_interval = args.poll_interval().unwrap_or(options.poll_interval);
+    options.record = args.record.clone();
+    options.replay = args.replay.clone();
+    if let Some(notice) = backend::notice(options.backend, &roots, options.poll_interval) {
+        eprintln!("live_watch: {}", notice);
+    }
+    options.stdin = args.stdin;
+    let watcher = Watcher::new(&roots, &options, rt.clone())?;
+
+    let store = watcher.file_store(ListFilters::new());
+    eprintln!(
+        "live_watch: watching {} files, Ctrl-C to stop",
+        store.file_cache_ref().keys().count()
+    );
+    let store = Arc::new(RwLock::new(store));
+
+    let mut server = None;
+    if let Some(listener) = listener {
+        eprintln!("live_watch: serving http://{}", listener.local_addr()?);
+        let serving = Arc::new(Server::new(store.clone()));
+        let serve = server::serve(serving.clone(), listener);
+        rt.lock().unwrap().spawn(async {
+            if let Err(err) = serve.await {
+                eprintln!("live_watch: serving stopped: {}", err);
+            }
+        });
+        server = Some(serving);
+    }
+
+    let reporting = report_changes(watcher, store, options.debounce, output, server);
+    let handle = rt.lock().unwrap().spawn(reporting);
+    futures::executor::block_on(handle)??;
+
+    Ok(())
+}
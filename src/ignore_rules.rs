@@ -0,0 +1,198 @@
+//! The parts of the watched tree live_watch leaves alone
+//!
+//! Rules come from three places, later ones winning over earlier ones: the built-in defaults, every `.gitignore` and
+//! `.ignore` file inside the tree (deeper files winning over shallower ones), and the user's own globs (from the
+//! command line and the config file). All of them use gitignore syntax, so `!pattern` brings a path back.
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::MagicError;
+
+/// Left out of every tree, unless a rule says otherwise
+pub const DEFAULT_IGNORES: [&str; 3] = [".git/", "target/", "node_modules/"];
+
+/// Files inside the tree holding rules for their directory and everything below it
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// `$XDG_CONFIG_HOME/live_watch/ignore` (or `~/.config/live_watch/ignore`), rules the user wants for every tree
+pub fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config_dir.join("live_watch").join("ignore"))
+}
+
+// Matchers are built against ".", every path handed to them is already relative to their directory
+fn matcher(lines: &[&str]) -> Result<Gitignore, MagicError> {
+    let mut builder = GitignoreBuilder::new(".");
+    for line in lines {
+        builder.add_line(None, line)?;
+    }
+    Ok(builder.build()?)
+}
+
+// Some(true) for ignored, Some(false) for brought back by a `!` rule, None when no rule says anything
+fn decision<T>(found: Match<T>) -> Option<bool> {
+    match found {
+        Match::None => None,
+        Match::Ignore(_) => Some(true),
+        Match::Whitelist(_) => Some(false),
+    }
+}
+
+// The ignore files found below one watched root
+struct RootRules {
+    root: PathBuf,
+    canonical_root: PathBuf,
+    // Directory (relative to the root) and its rules, parents before their children
+    files: Vec<(PathBuf, Gitignore)>,
+}
+
+impl RootRules {
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.root)
+            .or_else(|_| path.strip_prefix(&self.canonical_root))
+            .ok()
+    }
+}
+
+pub struct IgnoreRules {
+    defaults: Gitignore,
+    user: Gitignore,
+    // Behind a lock so the rules shared with the watcher follow it to another root
+    roots: RwLock<Vec<RootRules>>,
+    // Broken lines in ignore files, worth telling the user about but not worth failing over
+    errors: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Read the rules for the trees below `roots`, `user_globs` come on top of the config file
+    pub fn new(roots: &[PathBuf], user_globs: &[String]) -> Result<Self, MagicError> {
+        Self::with_config(roots, config_path().as_deref(), user_globs)
+    }
+
+    /// Like [`IgnoreRules::new`], reading the user's rules from `config` instead of [`config_path`]
+    pub fn with_config(
+        roots: &[PathBuf],
+        config: Option<&Path>,
+        user_globs: &[String],
+    ) -> Result<Self, MagicError> {
+        let mut errors = Vec::new();
+
+        let mut user = GitignoreBuilder::new(".");
+        if let Some(config) = config.filter(|config| config.is_file()) {
+            if let Some(err) = user.add(config) {
+                errors.push(err.to_string());
+            }
+        }
+        for glob in user_globs {
+            user.add_line(None, glob)?;
+        }
+
+        let mut rules = IgnoreRules {
+            defaults: matcher(&DEFAULT_IGNORES)?,
+            user: user.build()?,
+            roots: RwLock::new(Vec::new()),
+            errors: Vec::new(),
+        };
+        errors.extend(rules.switch_roots(roots));
+        rules.errors = errors;
+
+        Ok(rules)
+    }
+
+    pub fn errors_ref(&self) -> &Vec<String> {
+        &self.errors
+    }
+
+    /// Read the ignore files below `roots` instead of the ones below the roots so far, returns the broken lines
+    pub fn switch_roots(&self, roots: &[PathBuf]) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut all_root_rules = Vec::new();
+        for root in roots {
+            let mut root_rules = RootRules {
+                root: root.to_path_buf(),
+                canonical_root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+                files: Vec::new(),
+            };
+            self.read_ignore_files(&mut root_rules, Path::new(""), &mut errors);
+            all_root_rules.push(root_rules);
+        }
+        *self.roots.write().unwrap() = all_root_rules;
+
+        errors
+    }
+
+    /// Pick up the ignore files of `dir` and (unless ignored) of the directories below it
+    fn read_ignore_files(&self, root_rules: &mut RootRules, dir: &Path, errors: &mut Vec<String>) {
+        let absolute = root_rules.root.join(dir);
+
+        let mut builder = GitignoreBuilder::new(".");
+        let mut any = false;
+        for name in IGNORE_FILES {
+            let file = absolute.join(name);
+            if file.is_file() {
+                if let Some(err) = builder.add(&file) {
+                    errors.push(err.to_string());
+                }
+                any = true;
+            }
+        }
+        if any {
+            match builder.build() {
+                Ok(gitignore) => root_rules.files.push((dir.to_path_buf(), gitignore)),
+                Err(err) => errors.push(err.to_string()),
+            }
+        }
+
+        let Ok(entries) = std::fs::read_dir(&absolute) else {
+            return;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let child = dir.join(entry.file_name());
+            if is_dir && !self.is_ignored_relative(root_rules, &child, true) {
+                self.read_ignore_files(root_rules, &child, errors);
+            }
+        }
+    }
+
+    fn is_ignored_relative(&self, root_rules: &RootRules, relative: &Path, is_dir: bool) -> bool {
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        if let Some(ignored) = decision(self.user.matched_path_or_any_parents(relative, is_dir)) {
+            return ignored;
+        }
+        for (dir, gitignore) in root_rules.files.iter().rev() {
+            let inside = match relative.strip_prefix(dir) {
+                Ok(inside) if !inside.as_os_str().is_empty() => inside,
+                _ => continue,
+            };
+            if let Some(ignored) = decision(gitignore.matched_path_or_any_parents(inside, is_dir)) {
+                return ignored;
+            }
+        }
+        decision(self.defaults.matched_path_or_any_parents(relative, is_dir)).unwrap_or(false)
+    }
+
+    /// Whether `path` (inside a root, as walked or as reported by notify) should be left alone
+    ///
+    /// Paths outside every root are never ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.roots.read().unwrap().iter().any(|root_rules| {
+            root_rules
+                .relative(path)
+                .is_some_and(|relative| self.is_ignored_relative(root_rules, relative, is_dir))
+        })
+    }
+
+    /// Like [`IgnoreRules::is_ignored`], asking the filesystem whether `path` is a directory
+    pub fn is_ignored_path(&self, path: &Path) -> bool {
+        self.is_ignored(path, path.is_dir())
+    }
+}
@@ -0,0 +1,147 @@
+//! Changes as JSON Lines, one object per line, for scripts and CI to consume
+//!
+//! ```text
+//! {"timestamp":"2026-10-19T07:25:23.418Z","kind":"changed","path":"src/main.rs","absolute_path":"/home/me/src/main.rs","size":812,"sha256":"9f86d0…"}
+//! ```
+//!
+//! `kind` is one of `created`, `changed`, `renamed` (with `from` and `absolute_from`) or `deleted` (without `size` and
+//! `sha256`). `content` or `diff` are only there when asked for.
+
+use std::path::Path;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::changes::{Change, ChangeKind};
+use crate::cmd_args::InlineArg;
+use crate::files::FileCache;
+use crate::string_tools::line_diff;
+
+/// What else goes into every event that has content
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Inline {
+    /// The whole text of the file
+    Content,
+    /// What changed since the previous event for the file, as a unified diff hunk
+    Diff,
+}
+
+impl From<InlineArg> for Inline {
+    fn from(inline: InlineArg) -> Self {
+        match inline {
+            InlineArg::Content => Inline::Content,
+            InlineArg::Diff => Inline::Diff,
+        }
+    }
+}
+
+/// Size and SHA-256 of a file left unloaded for its size, hashed from disk a block at a time
+fn on_disk(path: &Path) -> Option<(u64, String)> {
+    let size = std::fs::metadata(path).ok()?.len();
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).ok()?;
+    Some((size, format!("{:x}", hasher.finalize())))
+}
+
+// Paths that are not utf8 are written with replacement characters, JSON strings have to be utf8
+fn lossy(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[derive(Serialize, PartialEq, Clone, Debug)]
+pub struct JsonEvent {
+    /// RFC 3339, UTC, in milliseconds
+    pub timestamp: String,
+    pub kind: ChangeKind,
+    /// The Watch List key, starting with the name of the watched root
+    pub path: String,
+    pub absolute_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub absolute_from: Option<String>,
+    /// In bytes, None when the file is gone
+    pub size: Option<u64>,
+    /// Hex encoded SHA-256 of the content, None when the file is gone
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+impl JsonEvent {
+    /// Describe a change to `key` (moved from `from` when renamed), the cache already holds the new state
+    ///
+    /// `previous` is the text the file had before the change, what a diff is taken against.
+    pub fn new(
+        kind: ChangeKind,
+        key: &Path,
+        from: Option<&Path>,
+        file_cache: &FileCache,
+        previous: Option<&str>,
+        inline: Option<Inline>,
+    ) -> Self {
+        let text = match kind {
+            ChangeKind::Deleted => None,
+            _ => file_cache
+                .file_ref(key)
+                .map(|file| file.file_form_ref().text_ref()),
+        };
+        let diff = match (inline, kind, text) {
+            (Some(Inline::Diff), ChangeKind::Created, Some(text)) => {
+                Some(line_diff("", text, usize::MAX))
+            }
+            (Some(Inline::Diff), ChangeKind::Changed, Some(text)) => {
+                previous.map(|previous| line_diff(previous, text, usize::MAX))
+            }
+            _ => None,
+        };
+
+        let (size, sha256) = match (kind, text) {
+            (ChangeKind::Deleted, _) => (None, None),
+            (_, Some(text)) => (
+                Some(text.len() as u64),
+                Some(format!("{:x}", Sha256::digest(text.as_bytes()))),
+            ),
+            (_, None) => on_disk(&file_cache.absolute_path(key)).unzip(),
+        };
+
+        JsonEvent {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            kind,
+            path: lossy(key),
+            absolute_path: lossy(&file_cache.absolute_path(key)),
+            from: from.map(lossy),
+            absolute_from: from.map(|from| lossy(&file_cache.absolute_path(from))),
+            size,
+            sha256,
+            content: text
+                .filter(|_| inline == Some(Inline::Content))
+                .map(str::to_owned),
+            diff,
+        }
+    }
+
+    /// Describe a [`Change`], the cache already holds the new state
+    pub fn from_change(change: &Change, file_cache: &FileCache, inline: Option<Inline>) -> Self {
+        let text = file_cache
+            .file_ref(change.key_ref())
+            .map(|file| file.file_form_ref().text_ref());
+        JsonEvent::new(
+            change.kind(),
+            change.key_ref(),
+            change.from_ref(),
+            file_cache,
+            text.and_then(|text| change.previous_text(text)),
+            inline,
+        )
+    }
+
+    /// The event as one line of JSON, without the newline
+    pub fn to_line(&self) -> String {
+        // Nothing but strings, numbers and nulls, which always serialize
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -1,12 +1,26 @@
+pub mod changes;
 pub mod cmd_args;
+pub mod control;
 pub mod files;
+pub mod headless;
+pub mod ignore_rules;
+pub mod json_events;
+pub mod list_filters;
+#[cfg(feature = "gui")]
 pub mod live_watch;
+pub mod messages;
+pub mod notifications;
+pub mod search;
+pub mod server;
+pub mod triggers;
+pub mod watching;
+#[cfg(feature = "gui")]
 pub mod windows;
 
 // Tis but a scratch
 
 pub type MagicError = Box<dyn std::error::Error>;
-pub type WatcherX = notify::INotifyWatcher;
+pub type WatcherX = Box<dyn notify::Watcher + Send>;
 
 // For development placeholder
 pub fn panik() {
@@ -33,6 +47,7 @@ pub fn time_of_day() -> String {
     format!("{}:{} -- {}", time.hour(), time.minute(), time.second())
 }
 
+#[cfg(feature = "gui")]
 pub fn font_size_default() -> egui::FontId {
     egui::FontId::proportional(30.0)
 }
@@ -76,6 +91,7 @@ pub mod err_tools {
     }
 }
 
+#[cfg(feature = "gui")]
 pub mod eframe_tools {
     use egui::Ui;
     use std::collections::BTreeMap;
@@ -128,6 +144,12 @@ pub mod eframe_tools {
             self.selected_option.clone()
         }
 
+        pub fn select(&mut self, option: SelectedOption) {
+            //! Select an option from outside the combo box, as if the user had clicked it
+            self.selected_option = option;
+            self.event = Some(());
+        }
+
         pub fn modal_machine(&mut self, id: i64, ui: &mut Ui) {
             ui.push_id(id, |ui| {
                 eframe::egui::ComboBox::from_label(&self.name)
@@ -197,4 +219,58 @@ pub mod string_tools {
     pub fn tabber(amount: usize) -> String {
         quick_maker(amount, "\t")
     }
+
+    /// Where `new` stops matching `old`: (lines shared at the start, lines removed, lines added)
+    ///
+    /// Lines shared at the start and the end are skipped, whatever is left in between counts as removed (old) and
+    /// added (new) lines.
+    pub fn changed_lines(old: &[&str], new: &[&str]) -> (usize, usize, usize) {
+        let prefix = old
+            .iter()
+            .zip(new)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+
+        (
+            prefix,
+            old.len() - prefix - suffix,
+            new.len() - prefix - suffix,
+        )
+    }
+
+    /// The lines that changed between `old` and `new` as one unified diff hunk, empty when no line changed
+    ///
+    /// Past `max_lines` lines the hunk is cut short with a count of what was left out.
+    pub fn line_diff(old: &str, new: &str, max_lines: usize) -> String {
+        let old: Vec<&str> = old.lines().collect();
+        let new: Vec<&str> = new.lines().collect();
+        let (prefix, removed, added) = changed_lines(&old, &new);
+        if removed == 0 && added == 0 {
+            return String::new();
+        }
+
+        let mut diff = format!(
+            "@@ -{},{} +{},{} @@\n",
+            prefix + 1,
+            removed,
+            prefix + 1,
+            added
+        );
+        let removed_lines = old[prefix..prefix + removed].iter().map(|line| ('-', line));
+        let added_lines = new[prefix..prefix + added].iter().map(|line| ('+', line));
+        for (shown, (sign, line)) in removed_lines.chain(added_lines).enumerate() {
+            if shown == max_lines {
+                diff.push_str(&format!("... {} more lines\n", removed + added - shown));
+                break;
+            }
+            diff.push_str(&format!("{}{}\n", sign, line));
+        }
+        diff
+    }
 }
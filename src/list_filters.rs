@@ -0,0 +1,132 @@
+//! Quick filters on the Watch List: which files are listed, and which of those are loaded right away
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ListFilters {
+    // Lowercase, without the dot, empty lists every extension
+    extensions: Vec<String>,
+    max_file_size: Option<u64>,
+    max_depth: Option<usize>,
+    show_hidden: bool,
+}
+
+impl ListFilters {
+    pub fn new() -> Self {
+        ListFilters {
+            extensions: Vec::new(),
+            max_file_size: None,
+            max_depth: None,
+            show_hidden: true,
+        }
+    }
+
+    pub fn extensions_ref(&self) -> &Vec<String> {
+        &self.extensions
+    }
+
+    /// Only list files with one of these (comma or space separated) extensions, `rs, .toml` works as well as `rs toml`
+    pub fn set_extensions(&mut self, extensions: &str) {
+        self.extensions = extensions
+            .split([',', ' '])
+            .map(|extension| extension.trim_start_matches('.').to_lowercase())
+            .filter(|extension| !extension.is_empty())
+            .collect();
+    }
+
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Bigger files (in bytes) are still listed, but only loaded once they are opened
+    pub fn set_max_file_size(&mut self, max_file_size: Option<u64>) {
+        self.max_file_size = max_file_size;
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// How many directories deep to go, files directly inside the root are at depth 1
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+
+    pub fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.show_hidden = show_hidden;
+    }
+
+    /// Whether the file behind `key` (relative to the parent of the root, like every Watch List key) is listed
+    pub fn lists(&self, key: &Path) -> bool {
+        // The first component is the root itself
+        let inside: Vec<_> = key.components().skip(1).collect();
+
+        if self
+            .max_depth
+            .is_some_and(|max_depth| inside.len() > max_depth)
+        {
+            return false;
+        }
+        let hidden = inside
+            .iter()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden && !self.show_hidden {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        key.extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .is_some_and(|extension| self.extensions.contains(&extension))
+    }
+
+    /// Whether a listed file of `size` bytes is loaded without being opened
+    pub fn loads(&self, size: u64) -> bool {
+        self.max_file_size
+            .is_none_or(|max_file_size| size <= max_file_size)
+    }
+}
+
+impl Default for ListFilters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The size limit of the [`ListFilters`] in use, shared with the watcher so it does not read what would not be loaded
+#[derive(Clone, Debug)]
+pub struct SizeLimit {
+    // u64::MAX is no limit at all
+    max_file_size: Arc<AtomicU64>,
+}
+
+impl SizeLimit {
+    pub fn new() -> Self {
+        SizeLimit {
+            max_file_size: Arc::new(AtomicU64::new(u64::MAX)),
+        }
+    }
+
+    pub fn set(&self, max_file_size: Option<u64>) {
+        let max_file_size = max_file_size.unwrap_or(u64::MAX);
+        self.max_file_size.store(max_file_size, Ordering::Relaxed);
+    }
+
+    /// Whether a file of `size` bytes is loaded without being opened, see [`ListFilters::loads`]
+    pub fn loads(&self, size: u64) -> bool {
+        size <= self.max_file_size.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,19 @@
+pub mod activity_feed;
+pub mod cross_search;
+pub mod file_tree;
+pub mod file_view;
+pub mod filter_panel;
+pub mod follow_changes;
+pub mod highlight;
 pub mod live_watch_app;
+pub mod notify_panel;
+pub mod panes;
+pub mod path_filter;
+pub mod quick_open;
+pub mod search_bar;
 pub mod settings;
-pub mod watcher_keep;
+pub mod tabs;
+pub mod trigger_panel;
+pub mod watch_list;
 
 // Break
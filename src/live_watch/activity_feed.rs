@@ -0,0 +1,181 @@
+//! Activity feed: every filesystem event the watcher sees, for auditing what touches the watched tree
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::files::{inside_root, FileCache};
+use crate::live_watch::path_filter::PathFilter;
+use crate::watching::activity::{Activity, ActivityKind};
+
+// Oldest entries are dropped past this, from the feed as from what is held while paused
+const MAX_ACTIVITIES: usize = 10_000;
+
+// An activity with its paths turned into Watch List keys
+struct Entry {
+    activity: Activity,
+    keys: Vec<Option<PathBuf>>,
+}
+
+impl Entry {
+    fn shown_paths(&self) -> impl Iterator<Item = &std::path::Path> {
+        self.keys
+            .iter()
+            .zip(self.activity.paths_ref())
+            .map(|(key, path)| key.as_deref().unwrap_or(path))
+    }
+
+    /// What path globs are matched against, keys inside their root (like follow and notification globs)
+    fn matched_paths(&self) -> impl Iterator<Item = &std::path::Path> {
+        self.keys
+            .iter()
+            .zip(self.activity.paths_ref())
+            .map(|(key, path)| key.as_deref().map_or(path.as_path(), inside_root))
+    }
+}
+
+pub struct ActivityFeed {
+    open: bool,
+    paused: bool,
+    entries: VecDeque<Entry>,
+    // Arrived while paused, shown once the feed is resumed
+    held: VecDeque<Entry>,
+    kinds: [bool; ActivityKind::ALL.len()],
+    paths: PathFilter,
+}
+
+impl ActivityFeed {
+    pub fn new() -> Self {
+        ActivityFeed {
+            open: false,
+            paused: false,
+            entries: VecDeque::new(),
+            held: VecDeque::new(),
+            kinds: [true; ActivityKind::ALL.len()],
+            paths: PathFilter::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn push(entries: &mut VecDeque<Entry>, entry: Entry) {
+        if entries.len() == MAX_ACTIVITIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    fn resume(&mut self) {
+        self.paused = false;
+        for entry in std::mem::take(&mut self.held) {
+            Self::push(&mut self.entries, entry);
+        }
+    }
+
+    /// Add an activity from the watcher, whether or not the feed is open, so it is complete whenever it is opened
+    pub fn receive(&mut self, activity: Activity, file_cache: &FileCache) {
+        let keys = activity
+            .paths_ref()
+            .iter()
+            .map(|path| file_cache.relative_key(path))
+            .collect();
+        let entry = Entry { activity, keys };
+        match self.paused {
+            true => Self::push(&mut self.held, entry),
+            false => Self::push(&mut self.entries, entry),
+        }
+    }
+
+    fn shows(&self, entry: &Entry) -> bool {
+        self.kinds[entry.activity.kind().index()]
+            && entry.matched_paths().any(|path| self.paths.allows(path))
+    }
+
+    /// Shows the feed (while open), returns the key of a file the user clicked
+    pub fn show(&mut self, ctx: &egui::Context, file_cache: &FileCache) -> Option<PathBuf> {
+        if !self.open {
+            return None;
+        }
+
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new("Activity")
+            .resizable(true)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for kind in ActivityKind::ALL {
+                        ui.checkbox(&mut self.kinds[kind.index()], kind.label());
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    self.paths.ui(ui, "Paths:", "*.rs, src/**");
+                    match self.paused {
+                        true => {
+                            let resume = format!("Resume ({} new)", self.held.len());
+                            if ui.button(resume).clicked() {
+                                self.resume();
+                            }
+                        }
+                        false => {
+                            if ui.button("Pause").clicked() {
+                                self.paused = true;
+                            }
+                        }
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.entries.clear();
+                        self.held.clear();
+                    }
+                });
+                ui.separator();
+
+                let shown: Vec<&Entry> = self
+                    .entries
+                    .iter()
+                    .filter(|entry| self.shows(entry))
+                    .collect();
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show_rows(ui, row_height, shown.len(), |ui, rows| {
+                        for entry in &shown[rows] {
+                            let paths: Vec<String> = entry
+                                .shown_paths()
+                                .map(|path| path.display().to_string())
+                                .collect();
+                            let text = format!(
+                                "{}  {:<6}  {}",
+                                entry.activity.time_ref(),
+                                entry.activity.kind().label(),
+                                paths.join(" -> ")
+                            );
+                            let response = ui
+                                .selectable_label(false, egui::RichText::new(text).monospace())
+                                .on_hover_text(entry.activity.detail_ref());
+                            if response.clicked() {
+                                // The last path is where the file ended up (renames)
+                                clicked = entry
+                                    .keys
+                                    .iter()
+                                    .rev()
+                                    .flatten()
+                                    .find(|key| file_cache.contains(key))
+                                    .cloned();
+                            }
+                        }
+                    });
+            });
+        self.open = open;
+
+        clicked
+    }
+}
+
+impl Default for ActivityFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,155 @@
+//! "Search in all files": greps every cached file and lists the hits grouped by file
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use egui::Context;
+
+use crate::files::FileCache;
+use crate::search::{LineHit, Matcher, SearchMode};
+
+// Keeps the panel usable when a query matches nearly every line of a big file
+const MAX_HITS_PER_FILE: usize = 200;
+const MAX_PREVIEW_CHARS: usize = 120;
+
+struct FileHits {
+    version: u64,
+    hits: Vec<LineHit>,
+}
+
+pub struct CrossSearch {
+    open: bool,
+    query: String,
+    mode: SearchMode,
+    searched: Option<(String, SearchMode)>,
+    results: BTreeMap<PathBuf, FileHits>,
+    error: Option<String>,
+}
+
+impl CrossSearch {
+    pub fn new() -> Self {
+        CrossSearch {
+            open: false,
+            query: String::new(),
+            mode: SearchMode::Plain,
+            searched: None,
+            results: BTreeMap::new(),
+            error: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Searches files whose version changed since they were last searched, or everything when the query changed
+    fn refresh(&mut self, file_cache: &FileCache) {
+        let query = (self.query.clone(), self.mode);
+        if self.searched.as_ref() != Some(&query) {
+            self.results.clear();
+            self.error = None;
+            self.searched = Some(query);
+        }
+        if self.query.is_empty() || self.error.is_some() {
+            return;
+        }
+
+        let matcher = match Matcher::new(&self.query, &self.mode) {
+            Ok(matcher) => matcher,
+            Err(err) => {
+                self.error = Some(err.to_string());
+                return;
+            }
+        };
+
+        let mut present = HashSet::new();
+        for (path, file) in file_cache.files() {
+            present.insert(path);
+            let up_to_date = self
+                .results
+                .get(path)
+                .is_some_and(|searched| searched.version == file.version());
+            if !up_to_date {
+                let hits = matcher.line_hits(file.file_form_ref().text_ref(), MAX_HITS_PER_FILE);
+                let version = file.version();
+                self.results
+                    .insert(path.clone(), FileHits { version, hits });
+            }
+        }
+        // Deleted and renamed files
+        self.results.retain(|path, _| present.contains(path));
+    }
+
+    /// Shows the panel (while open), returns the file and line of a hit the user clicked
+    pub fn show(&mut self, ctx: &Context, file_cache: &FileCache) -> Option<(PathBuf, usize)> {
+        if ctx.input_mut(|i| {
+            i.consume_key(
+                egui::Modifiers::COMMAND | egui::Modifiers::SHIFT,
+                egui::Key::F,
+            )
+        }) {
+            self.open = true;
+        }
+        if !self.open {
+            return None;
+        }
+
+        self.refresh(file_cache);
+
+        let mut clicked = None;
+        let mut open = self.open;
+        egui::Window::new("Search In All Files")
+            .resizable(true)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Find:");
+                    ui.text_edit_singleline(&mut self.query);
+                });
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.mode, SearchMode::Plain, "Plain");
+                    ui.radio_value(&mut self.mode, SearchMode::CaseInsensitive, "Ignore Case");
+                    ui.radio_value(&mut self.mode, SearchMode::Regex, "Regex");
+                });
+
+                if let Some(err) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+                let files = self.results.values().filter(|found| !found.hits.is_empty());
+                let hits: usize = files.clone().map(|found| found.hits.len()).sum();
+                ui.label(format!("{} hits in {} files", hits, files.count()));
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (path, found) in &self.results {
+                        if found.hits.is_empty() {
+                            continue;
+                        }
+                        let title = format!("{} ({})", path.display(), found.hits.len());
+                        egui::CollapsingHeader::new(title)
+                            .id_source(path)
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for hit in &found.hits {
+                                    let preview: String =
+                                        hit.text.trim().chars().take(MAX_PREVIEW_CHARS).collect();
+                                    let text = format!("{}: {}", hit.line, preview);
+                                    if ui.selectable_label(false, text).clicked() {
+                                        clicked = Some((path.clone(), hit.line));
+                                    }
+                                }
+                            });
+                    }
+                });
+            });
+        self.open = open;
+
+        clicked
+    }
+}
+
+impl Default for CrossSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,221 @@
+//! Side panel tree of the watched root
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use egui::{Color32, RichText, Ui};
+
+use crate::files::FileCache;
+
+/// Which version of each file the user has last looked at
+#[derive(Default)]
+pub struct SeenVersions {
+    seen: HashMap<PathBuf, u64>,
+}
+
+impl SeenVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_seen(&mut self, key: &Path, file_cache: &FileCache) {
+        if let Some(file) = file_cache.file_ref(key) {
+            self.seen.insert(key.to_path_buf(), file.version());
+        }
+    }
+
+    /// Treat everything currently in the cache as already looked at
+    pub fn mark_all_seen(&mut self, file_cache: &FileCache) {
+        for (key, file) in file_cache.files() {
+            self.seen.insert(key.clone(), file.version());
+        }
+    }
+
+    /// Keep what was seen of a file that moved
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(version) = self.seen.remove(from) {
+            self.seen.insert(to.to_path_buf(), version);
+        }
+    }
+
+    /// True when the file changed (or appeared) since the user last looked at it
+    pub fn is_changed(&self, key: &Path, file_cache: &FileCache) -> bool {
+        match (self.seen.get(key), file_cache.file_ref(key)) {
+            (Some(seen), Some(file)) => *seen != file.version(),
+            (None, Some(_)) => true,
+            (_, None) => false,
+        }
+    }
+}
+
+// How long a flashed file stays highlighted
+const FLASH_DURATION: Duration = Duration::from_millis(1500);
+
+#[derive(Default)]
+struct TreeNode {
+    // Some for files, None for directories
+    key: Option<PathBuf>,
+    children: BTreeMap<String, TreeNode>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, key: &Path) {
+        let mut node = self;
+        for component in key.components() {
+            let name = component.as_os_str().to_string_lossy().into_owned();
+            node = node.children.entry(name).or_default();
+        }
+        node.key = Some(key.to_path_buf());
+    }
+
+    fn changed_count(&self, seen: &SeenVersions, file_cache: &FileCache) -> usize {
+        match &self.key {
+            Some(key) => usize::from(seen.is_changed(key, file_cache)),
+            None => self
+                .children
+                .values()
+                .map(|child| child.changed_count(seen, file_cache))
+                .sum(),
+        }
+    }
+}
+
+pub struct FileTree {
+    root: TreeNode,
+    listing_version: Option<u64>,
+    seen: SeenVersions,
+    flashes: HashMap<PathBuf, Instant>,
+}
+
+// Everything a node needs to know to draw itself
+struct NodeContext<'a> {
+    selected: &'a Path,
+    seen: &'a SeenVersions,
+    flashes: &'a HashMap<PathBuf, Instant>,
+    file_cache: &'a FileCache,
+}
+
+impl FileTree {
+    pub fn new() -> Self {
+        FileTree {
+            root: TreeNode::default(),
+            listing_version: None,
+            seen: SeenVersions::new(),
+            flashes: HashMap::new(),
+        }
+    }
+
+    /// Briefly highlight `key` to draw attention to it
+    pub fn flash(&mut self, key: PathBuf) {
+        self.flashes.insert(key, Instant::now());
+    }
+
+    fn rebuild(&mut self, file_cache: &FileCache) {
+        if self.listing_version == Some(file_cache.listing_version()) {
+            return;
+        }
+        // Files that are there from the start are not news
+        if self.listing_version.is_none() {
+            self.seen.mark_all_seen(file_cache);
+        }
+
+        let mut root = TreeNode::default();
+        file_cache.keys().for_each(|key| root.insert(key));
+        self.root = root;
+        self.listing_version = Some(file_cache.listing_version());
+    }
+
+    /// Shows the tree, returns the key of a file the user clicked
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        file_cache: &FileCache,
+        selected: &Path,
+    ) -> Option<PathBuf> {
+        self.rebuild(file_cache);
+        self.seen.mark_seen(selected, file_cache);
+        self.flashes
+            .retain(|_, flashed| flashed.elapsed() < FLASH_DURATION);
+
+        let context = NodeContext {
+            selected,
+            seen: &self.seen,
+            flashes: &self.flashes,
+            file_cache,
+        };
+        let mut clicked = None;
+        egui::ScrollArea::both()
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for (name, node) in &self.root.children {
+                    let path = Path::new(name);
+                    show_node(ui, path, node, &context, &mut clicked);
+                }
+            });
+
+        clicked
+    }
+}
+
+impl Default for FileTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fades from the selection color to nothing over [`FLASH_DURATION`]
+fn flash_color(ui: &Ui, flashed: &Instant) -> Color32 {
+    let left = 1.0 - flashed.elapsed().as_secs_f32() / FLASH_DURATION.as_secs_f32();
+    ui.visuals()
+        .selection
+        .bg_fill
+        .linear_multiply(left.clamp(0.0, 1.0))
+}
+
+fn show_node(
+    ui: &mut Ui,
+    path: &Path,
+    node: &TreeNode,
+    context: &NodeContext,
+    clicked: &mut Option<PathBuf>,
+) {
+    let changed = node.changed_count(context.seen, context.file_cache);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    if let Some(key) = &node.key {
+        let mut text = RichText::new(format!("📄 {}", name));
+        if changed > 0 {
+            text = RichText::new(format!("📄 {} ●", name)).strong();
+        }
+        if let Some(flashed) = context.flashes.get(key) {
+            text = text.background_color(flash_color(ui, flashed));
+        }
+        if ui.selectable_label(key == context.selected, text).clicked() {
+            *clicked = Some(key.clone());
+        }
+        return;
+    }
+
+    let title = match changed {
+        0 => format!("📁 {}", name),
+        changed => format!("📁 {} ({})", name, changed),
+    };
+    // Unfold the way to a flashed file so it can be seen
+    let holds_flash = context.flashes.keys().any(|key| key.starts_with(path));
+    egui::CollapsingHeader::new(title)
+        .id_source(path)
+        .default_open(path.components().count() == 1)
+        .open(holds_flash.then_some(true))
+        .show(ui, |ui| {
+            // Directories first, then files, each alphabetically
+            let (directories, files): (Vec<_>, Vec<_>) = node
+                .children
+                .iter()
+                .partition(|(_, child)| child.key.is_none());
+            for (child_name, child) in directories.into_iter().chain(files) {
+                let child_path = path.join(child_name);
+                show_node(ui, &child_path, child, context, clicked);
+            }
+        });
+}
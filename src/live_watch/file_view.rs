@@ -0,0 +1,484 @@
+//! Renders the contents of a watched file in whichever [`TextMode`] the user picked
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use egui::text::{LayoutJob, LayoutSection, TextFormat};
+use egui::{Align, Align2, Color32, FontId, Ui, WidgetText};
+
+use crate::eframe_tools::make_rich;
+use crate::files::{File, Segment};
+use crate::live_watch::highlight::HighlightCache;
+use crate::live_watch::search_bar::SearchBar;
+use crate::live_watch::settings::{Settings, TextMode};
+
+// Gives every view its own scroll position
+static NEXT_VIEW_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Per view state of the file display
+pub struct FileView {
+    id: egui::Id,
+    text_mode: TextMode,
+    goto_line: String,
+    jump_to_line: Option<usize>,
+    search: SearchBar,
+}
+
+impl FileView {
+    fn get_text_mode_ref(&self) -> &TextMode {
+        &self.text_mode
+    }
+    fn get_text_mode_mut(&mut self) -> &mut TextMode {
+        &mut self.text_mode
+    }
+    fn get_goto_line_mut(&mut self) -> &mut String {
+        &mut self.goto_line
+    }
+    fn get_jump_to_line_mut(&mut self) -> &mut Option<usize> {
+        &mut self.jump_to_line
+    }
+    fn get_search_ref(&self) -> &SearchBar {
+        &self.search
+    }
+    fn get_search_mut(&mut self) -> &mut SearchBar {
+        &mut self.search
+    }
+}
+
+impl FileView {
+    pub fn new(text_mode: TextMode) -> Self {
+        let id = egui::Id::new(("file_view", NEXT_VIEW_ID.fetch_add(1, Ordering::Relaxed)));
+        FileView {
+            id,
+            text_mode,
+            goto_line: String::new(),
+            jump_to_line: None,
+            search: SearchBar::new(),
+        }
+    }
+
+    pub fn text_mode_ref(&self) -> &TextMode {
+        self.get_text_mode_ref()
+    }
+
+    pub fn text_mode_mut(&mut self) -> &mut TextMode {
+        self.get_text_mode_mut()
+    }
+
+    pub fn search_ref(&self) -> &SearchBar {
+        self.get_search_ref()
+    }
+
+    pub fn search_mut(&mut self) -> &mut SearchBar {
+        self.get_search_mut()
+    }
+
+    /// Scroll the given (1 based) line into view on the next frame
+    pub fn jump_to_line(&mut self, line: usize) {
+        *self.get_jump_to_line_mut() = Some(line.max(1));
+    }
+
+    /// Shows the "Go to line" input
+    pub fn goto_line_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Go to line:");
+            let response =
+                ui.add(egui::TextEdit::singleline(self.get_goto_line_mut()).desired_width(60.0));
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Go").clicked() || entered {
+                if let Ok(line) = self.goto_line.trim().parse::<usize>() {
+                    self.jump_to_line(line);
+                }
+            }
+        });
+    }
+}
+
+fn render_option(text_mode: &TextMode) -> impl Fn(&mut Ui) {
+    match text_mode {
+        TextMode::Newline => |ui: &mut Ui| {
+            ui.separator();
+        },
+        TextMode::AllNewline => |ui: &mut Ui| {
+            ui.separator();
+        },
+        TextMode::Selectable => |_ui: &mut Ui| {},
+        TextMode::Standard => |_ui: &mut Ui| {},
+    }
+}
+
+fn gutter_font(font_id: &FontId) -> FontId {
+    FontId::monospace(font_id.size)
+}
+
+fn gutter_text(line: usize, digits: usize) -> String {
+    format!("{:>digits$}", line)
+}
+
+/// Lay out the text for the Selectable(TextEdit) mode, honouring the word wrap setting
+///
+/// `prepared` (highlighted and/or marked up text) is used as long as the text has not been edited away from it
+fn selectable_layouter(
+    font_id: FontId,
+    word_wrap: bool,
+    prepared: Option<LayoutJob>,
+) -> impl FnMut(&Ui, &str, f32) -> std::sync::Arc<egui::Galley> {
+    move |ui: &Ui, string: &str, wrap_width: f32| {
+        let wrap_width = if word_wrap { wrap_width } else { f32::INFINITY };
+        let mut layout_job = match &prepared {
+            Some(job) if job.text == string => job.clone(),
+            _ => {
+                let color = ui.visuals().widgets.inactive.text_color();
+                LayoutJob::simple(string.to_owned(), font_id.clone(), color, 0.0)
+            }
+        };
+        layout_job.wrap.max_width = wrap_width;
+        ui.fonts(|f| f.layout_job(layout_job))
+    }
+}
+
+/// `line` without its trailing `\n` or `\r\n`, a label would lay that out as an empty row of its own
+fn without_line_end(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+fn job_without_line_end(mut job: LayoutJob) -> LayoutJob {
+    let end = without_line_end(&job.text).len();
+    job.text.truncate(end);
+    for section in &mut job.sections {
+        section.byte_range.start = section.byte_range.start.min(end);
+        section.byte_range.end = section.byte_range.end.min(end);
+    }
+    // An empty line still needs one (empty) section to be a row as high as the font
+    let first = job.sections.first().cloned();
+    job.sections
+        .retain(|section| !section.byte_range.is_empty());
+    if job.sections.is_empty() {
+        job.sections.extend(first);
+    }
+    job
+}
+
+fn plain_job(text: &str, font_id: &FontId, color: Color32) -> LayoutJob {
+    let format = TextFormat {
+        font_id: font_id.clone(),
+        color,
+        ..Default::default()
+    };
+    LayoutJob::single_section(text.to_owned(), format)
+}
+
+fn overlaps(matches: &[Range<usize>], segment: &Segment) -> bool {
+    let first = matches.partition_point(|found| found.end <= segment.start);
+    matches
+        .get(first)
+        .is_some_and(|found| found.start < segment.end)
+}
+
+/// Paints the background of search matches into `job`, which shows the file text starting at `offset`
+fn mark_matches(
+    mut job: LayoutJob,
+    offset: usize,
+    matches: &[Range<usize>],
+    current: Option<&Range<usize>>,
+    ui: &Ui,
+) -> LayoutJob {
+    let end = offset + job.text.len();
+    let first = matches.partition_point(|found| found.end <= offset);
+    let matches: Vec<&Range<usize>> = matches[first..]
+        .iter()
+        .take_while(|found| found.start < end)
+        .collect();
+    if matches.is_empty() {
+        return job;
+    }
+
+    let match_color = Color32::from_rgba_unmultiplied(255, 210, 0, 70);
+    let current_color = ui.visuals().selection.bg_fill;
+    let mut sections = Vec::with_capacity(job.sections.len() + matches.len() * 2);
+    for section in job.sections.drain(..) {
+        let mut leading_space = section.leading_space;
+        let mut piece = |byte_range: Range<usize>, format: TextFormat| {
+            sections.push(LayoutSection {
+                leading_space: std::mem::take(&mut leading_space),
+                byte_range,
+                format,
+            });
+        };
+
+        let mut at = section.byte_range.start;
+        for found in &matches {
+            let start = found.start.saturating_sub(offset).max(at);
+            let stop = (found.end - offset).min(section.byte_range.end);
+            if start >= stop {
+                continue;
+            }
+            if at < start {
+                piece(at..start, section.format.clone());
+            }
+            let mut format = section.format.clone();
+            format.background = match current == Some(*found) {
+                true => current_color,
+                false => match_color,
+            };
+            piece(start..stop, format);
+            at = stop;
+        }
+        if at < section.byte_range.end {
+            piece(at..section.byte_range.end, section.format);
+        }
+    }
+    job.sections = sections;
+
+    job
+}
+
+/// One line of the file, optionally preceded by its line number
+fn show_row(
+    ui: &mut Ui,
+    content: impl Into<WidgetText>,
+    gutter: Option<(usize, usize)>,
+    font_id: &FontId,
+    word_wrap: bool,
+) -> egui::Response {
+    match gutter {
+        Some((line, digits)) => {
+            ui.horizontal_top(|ui| {
+                let number = make_rich(gutter_text(line, digits), gutter_font(font_id)).weak();
+                ui.label(number);
+                ui.add(egui::Label::new(content).wrap(word_wrap));
+            })
+            .response
+        }
+        None => ui.add(egui::Label::new(content).wrap(word_wrap)),
+    }
+}
+
+fn gutter_width(ui: &Ui, font_id: &FontId, digits: usize) -> f32 {
+    ui.fonts(|f| f.glyph_width(&gutter_font(font_id), '0')) * digits as f32
+}
+
+/// Paint the line numbers of `galley` (drawn at `origin`) into `gutter`, and scroll to `jump_to_line`
+fn paint_gutter(
+    ui: &Ui,
+    galley: &egui::Galley,
+    origin: egui::Pos2,
+    gutter: Option<egui::Rect>,
+    digits: usize,
+    font_id: &FontId,
+    jump_to_line: Option<usize>,
+) {
+    // Galley rows that follow a newline start a new line of the file, wrapped rows do not
+    let mut line = 1;
+    let mut starts_line = true;
+    for row in &galley.rows {
+        let row_rect = row.rect.translate(origin.to_vec2());
+        if starts_line {
+            if let Some(gutter) = gutter {
+                ui.painter().text(
+                    egui::pos2(gutter.right(), row_rect.top()),
+                    Align2::RIGHT_TOP,
+                    gutter_text(line, digits),
+                    gutter_font(font_id),
+                    ui.visuals().weak_text_color(),
+                );
+            }
+            if jump_to_line == Some(line) {
+                ui.scroll_to_rect(row_rect, Some(Align::TOP));
+            }
+        }
+        if row.ends_with_newline {
+            line += 1;
+        }
+        starts_line = row.ends_with_newline;
+    }
+}
+
+/// The whole file as one galley (one row per line would be laid out anew every frame), numbered from its rows
+fn display_standard(
+    file: &File,
+    settings: &Settings,
+    mut job: LayoutJob,
+    jump_to_line: Option<usize>,
+    ui: &mut Ui,
+) {
+    let font_id = settings.font_size_ref();
+    let digits = file.file_form_ref().line_count().to_string().len();
+
+    ui.horizontal_top(|ui| {
+        let gutter = settings.line_numbers_ref().then(|| {
+            let width = gutter_width(ui, font_id, digits);
+            ui.allocate_space(egui::vec2(width, 0.0)).1
+        });
+
+        job.wrap.max_width = match settings.word_wrap_ref() {
+            true => ui.available_width(),
+            false => f32::INFINITY,
+        };
+        let galley = ui.fonts(|f| f.layout_job(job));
+        let (rect, _) = ui.allocate_exact_size(galley.size(), egui::Sense::hover());
+        paint_gutter(ui, &galley, rect.min, gutter, digits, font_id, jump_to_line);
+        ui.painter().galley(rect.min, galley);
+    });
+}
+
+fn display_selectable(
+    file: &File,
+    settings: &Settings,
+    prepared: Option<LayoutJob>,
+    jump_to_line: Option<usize>,
+    ui: &mut Ui,
+) {
+    let file_form = file.file_form_ref();
+    let font_id = settings.font_size_ref();
+    let line_numbers = *settings.line_numbers_ref();
+    let digits = file_form.line_count().to_string().len();
+
+    // TODO: this is so unnecessarily expensive, but egui doesnt support selectable text properly yet... either store this somewhere as a string and rip it out or wait for egui to do its thing...
+    let mut text = file_form.text_ref().to_owned();
+    let mut layouter = selectable_layouter(font_id.clone(), *settings.word_wrap_ref(), prepared);
+
+    ui.horizontal_top(|ui| {
+        let gutter = line_numbers.then(|| {
+            let width = gutter_width(ui, font_id, digits);
+            ui.allocate_space(egui::vec2(width, 0.0)).1
+        });
+
+        let output = egui::TextEdit::multiline(&mut text)
+            .desired_width(f32::INFINITY)
+            .layouter(&mut layouter)
+            .show(ui);
+        let origin = output.text_draw_pos;
+        paint_gutter(
+            ui,
+            &output.galley,
+            origin,
+            gutter,
+            digits,
+            font_id,
+            jump_to_line,
+        );
+    });
+}
+
+/// Shows the search bar and `file` inside a scroll area, only a `focused` view takes keyboard shortcuts
+pub fn display_file(
+    file: Option<&File>,
+    settings: &Settings,
+    highlight_cache: &mut HighlightCache,
+    view: &mut FileView,
+    focused: bool,
+    ui: &mut Ui,
+) {
+    let word_wrap = *settings.word_wrap_ref();
+    let text_mode = *view.text_mode_ref();
+    let view_id = view.id;
+    let font_id = settings.font_size_ref();
+    let text_color = ui.visuals().widgets.inactive.text_color();
+
+    if let Some(offset) = view.search_mut().show(ui, file, focused) {
+        if let Some(file) = file {
+            view.jump_to_line(file.file_form_ref().line_of(offset));
+        }
+    }
+
+    let mut subfn = |ui: &mut Ui| -> Option<()> {
+        let file = file?;
+        let file_form = file.file_form_ref();
+        let jump_to_line = view.get_jump_to_line_mut().take();
+        let matches = view.get_search_ref().matches();
+        let current_match = view.get_search_ref().current_match();
+
+        if let TextMode::Selectable | TextMode::Standard = text_mode {
+            let highlighted = highlight_cache
+                .layout_jobs(
+                    file,
+                    &text_mode,
+                    settings.syntax_theme_ref(),
+                    font_id,
+                    text_color,
+                )
+                .and_then(|jobs| jobs.first().cloned());
+            let prepared = match (highlighted, matches.is_empty()) {
+                (highlighted, true) => highlighted,
+                (highlighted, false) => {
+                    let job = highlighted
+                        .unwrap_or_else(|| plain_job(file_form.text_ref(), font_id, text_color));
+                    Some(mark_matches(job, 0, matches, current_match, ui))
+                }
+            };
+            match text_mode {
+                TextMode::Selectable => {
+                    display_selectable(file, settings, prepared, jump_to_line, ui)
+                }
+                _ => {
+                    let job = prepared
+                        .unwrap_or_else(|| plain_job(file_form.text_ref(), font_id, text_color));
+                    display_standard(file, settings, job, jump_to_line, ui);
+                }
+            }
+            return None;
+        }
+
+        let segments = file_form.get_file_text(&text_mode)?;
+        let highlighted = highlight_cache.layout_jobs(
+            file,
+            &text_mode,
+            settings.syntax_theme_ref(),
+            font_id,
+            text_color,
+        );
+        let digits = file_form.line_count().to_string().len();
+
+        let mut jump_to_line = jump_to_line;
+        for (index, segment) in segments.iter().enumerate() {
+            let line = file_form.line_of(segment.start);
+            let gutter = settings.line_numbers_ref().then_some((line, digits));
+            let text = without_line_end(file_form.segment_text(segment));
+            let response = match (highlighted, overlaps(matches, segment)) {
+                (Some(jobs), false) => {
+                    let job = job_without_line_end(jobs[index].clone());
+                    show_row(ui, job, gutter, font_id, word_wrap)
+                }
+                (None, false) => {
+                    let rich_text = make_rich(text.to_owned(), font_id.clone());
+                    show_row(ui, rich_text, gutter, font_id, word_wrap)
+                }
+                (highlighted, true) => {
+                    let job = match highlighted {
+                        Some(jobs) => job_without_line_end(jobs[index].clone()),
+                        None => plain_job(text, font_id, text_color),
+                    };
+                    let job = mark_matches(job, segment.start, matches, current_match, ui);
+                    show_row(ui, job, gutter, font_id, word_wrap)
+                }
+            };
+            render_option(&text_mode)(ui);
+
+            // Blank lines may not have a row of their own, so settle for the next one that does
+            if jump_to_line.is_some_and(|target| line >= target) {
+                response.scroll_to_me(Some(Align::TOP));
+                jump_to_line = None;
+            }
+        }
+
+        None
+    };
+
+    // Wrapped text only ever needs to scroll vertically, unwrapped lines scroll sideways as well
+    let scroll_area = if word_wrap {
+        egui::ScrollArea::vertical()
+    } else {
+        egui::ScrollArea::both()
+    };
+
+    scroll_area
+        .id_source(view_id)
+        .stick_to_bottom(*settings.tail_ref())
+        .auto_shrink([false; 2])
+        .show(ui, |ui| {
+            subfn(ui);
+            ui.allocate_space(ui.available_size());
+        });
+}
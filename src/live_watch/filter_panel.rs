@@ -0,0 +1,90 @@
+//! Window for the quick filters on the Watch List
+
+use egui::Context;
+
+use crate::list_filters::ListFilters;
+
+const BYTES_PER_KB: u64 = 1024;
+
+pub struct FilterPanel {
+    open: bool,
+    extensions: String,
+    limit_size: bool,
+    max_size_kb: u64,
+    limit_depth: bool,
+    max_depth: usize,
+    show_hidden: bool,
+}
+
+impl FilterPanel {
+    pub fn new(filters: &ListFilters) -> Self {
+        FilterPanel {
+            open: false,
+            extensions: filters.extensions_ref().join(", "),
+            limit_size: filters.max_file_size().is_some(),
+            max_size_kb: filters
+                .max_file_size()
+                .map_or(1024, |max_file_size| max_file_size / BYTES_PER_KB),
+            limit_depth: filters.max_depth().is_some(),
+            max_depth: filters.max_depth().unwrap_or(3),
+            show_hidden: filters.show_hidden(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn filters(&self) -> ListFilters {
+        let mut filters = ListFilters::new();
+        filters.set_extensions(&self.extensions);
+        filters.set_max_file_size(self.limit_size.then_some(self.max_size_kb * BYTES_PER_KB));
+        filters.set_max_depth(self.limit_depth.then_some(self.max_depth));
+        filters.set_show_hidden(self.show_hidden);
+        filters
+    }
+
+    /// Shows the window (while open), returns the filters once the user applies them
+    pub fn show(&mut self, ctx: &Context, current: &ListFilters) -> Option<ListFilters> {
+        let mut applied = None;
+        let mut open = self.open;
+        egui::Window::new("Filters")
+            .resizable(false)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Only Extensions:");
+                    let edit = egui::TextEdit::singleline(&mut self.extensions)
+                        .hint_text("rs, toml, md")
+                        .desired_width(160.0);
+                    ui.add(edit);
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.limit_size, "Load files up to");
+                    let drag = egui::DragValue::new(&mut self.max_size_kb)
+                        .clamp_range(1..=1_048_576)
+                        .suffix(" KB");
+                    ui.add_enabled(self.limit_size, drag);
+                })
+                .response
+                .on_hover_text("Bigger files stay listed and load when opened");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.limit_depth, "Directories deep at most");
+                    let drag = egui::DragValue::new(&mut self.max_depth).clamp_range(1..=64);
+                    ui.add_enabled(self.limit_depth, drag);
+                });
+                ui.checkbox(&mut self.show_hidden, "Show Hidden Files");
+
+                let filters = self.filters();
+                if ui
+                    .add_enabled(filters != *current, egui::Button::new("Apply"))
+                    .clicked()
+                {
+                    applied = Some(filters);
+                }
+            });
+        self.open = open;
+
+        applied
+    }
+}
@@ -0,0 +1,120 @@
+//! Opt in "follow changes": bring whichever file just changed to the user's attention
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use egui::Ui;
+
+use crate::files::{inside_root, CacheUpdate};
+use crate::live_watch::path_filter::PathFilter;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum FollowMode {
+    /// Show the changed file
+    Switch,
+    /// Only highlight the changed file in the file list
+    Flash,
+}
+
+/// What should happen to a file that changed
+pub enum Follow {
+    Switch(PathBuf),
+    Flash(PathBuf),
+}
+
+pub struct FollowChanges {
+    enabled: bool,
+    mode: FollowMode,
+    // Globs matched against the path inside the root, an empty filter lets everything through
+    only: PathFilter,
+    never: PathFilter,
+    min_interval: Duration,
+    last_switch: Option<Instant>,
+}
+
+impl FollowChanges {
+    pub fn new() -> Self {
+        FollowChanges {
+            enabled: false,
+            mode: FollowMode::Switch,
+            only: PathFilter::new(),
+            never: PathFilter::new(),
+            min_interval: Duration::from_secs(2),
+            last_switch: None,
+        }
+    }
+
+    /// Start following changes in `mode`
+    pub fn enable(&mut self, mode: FollowMode) {
+        self.enabled = true;
+        self.mode = mode;
+    }
+
+    fn wants(&self, key: &Path) -> bool {
+        let inside = inside_root(key);
+        self.only.allows(inside) && !self.never.matches(inside)
+    }
+
+    /// What to do about the files the watcher just changed
+    ///
+    /// At most one switch happens per `min_interval`, changes in between are only flashed so a noisy file cannot
+    /// keep taking over the view.
+    pub fn follow(&mut self, updates: &[CacheUpdate]) -> Vec<Follow> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        let mut follows = Vec::new();
+        for update in updates {
+            let CacheUpdate::Changed(key) = update else {
+                continue;
+            };
+            if !self.wants(key) {
+                continue;
+            }
+
+            let rate_limited = self
+                .last_switch
+                .is_some_and(|last| last.elapsed() < self.min_interval);
+            match (self.mode, rate_limited) {
+                (FollowMode::Switch, false) => {
+                    self.last_switch = Some(Instant::now());
+                    follows.push(Follow::Switch(key.clone()));
+                }
+                _ => follows.push(Follow::Flash(key.clone())),
+            }
+        }
+
+        follows
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.enabled, "Follow Changes");
+            if !self.enabled {
+                return;
+            }
+            ui.radio_value(&mut self.mode, FollowMode::Switch, "Switch To File");
+            ui.radio_value(&mut self.mode, FollowMode::Flash, "Flash In List");
+
+            let mut seconds = self.min_interval.as_secs_f32();
+            ui.label("At most every");
+            let drag = egui::DragValue::new(&mut seconds)
+                .clamp_range(0.0..=60.0)
+                .speed(0.1)
+                .suffix("s");
+            if ui.add(drag).changed() {
+                self.min_interval = Duration::from_secs_f32(seconds);
+            }
+
+            self.only.ui(ui, "Only:", "src/**/*.rs, *.toml");
+            self.never.ui(ui, "Never:", "*.log");
+        });
+    }
+}
+
+impl Default for FollowChanges {
+    fn default() -> Self {
+        Self::new()
+    }
+}
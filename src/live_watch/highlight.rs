@@ -0,0 +1,648 @@
+//! Syntax highlighting for the file view
+//!
+//! A file is tokenized once per loaded version. The colored [`LayoutJob`]s built from those tokens are
+//! cached as well, so a frame only has to clone what it paints.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use egui::text::{LayoutJob, TextFormat};
+use egui::{Color32, FontId};
+
+use crate::files::{CacheUpdate, File, FileCache, Segment};
+use crate::live_watch::settings::{SyntaxTheme, TextMode};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    Rust,
+    Toml,
+    Json,
+    Yaml,
+    Shell,
+    Log,
+    Plain,
+}
+
+impl Language {
+    /// Pick a language by file name/extension, falling back to sniffing the content
+    pub fn detect(path: &Path, text: &str) -> Language {
+        Self::from_path(path).unwrap_or_else(|| Self::sniff(text))
+    }
+
+    fn from_path(path: &Path) -> Option<Language> {
+        match path.file_name()?.to_str()? {
+            "Cargo.lock" => return Some(Language::Toml),
+            ".bashrc" | ".bash_profile" | ".profile" | ".zshrc" => return Some(Language::Shell),
+            _ => {}
+        }
+
+        let language = match path.extension()?.to_str()? {
+            "rs" => Language::Rust,
+            "toml" => Language::Toml,
+            "json" | "jsonl" => Language::Json,
+            "yaml" | "yml" => Language::Yaml,
+            "sh" | "bash" | "zsh" => Language::Shell,
+            "log" | "out" => Language::Log,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    fn sniff(text: &str) -> Language {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .take(20)
+            .collect();
+        let first = match lines.first() {
+            Some(first) => *first,
+            None => return Language::Plain,
+        };
+
+        if let Some(interpreter) = first.strip_prefix("#!") {
+            let program = interpreter.rsplit(['/', ' ']).next().unwrap_or_default();
+            return match program {
+                "sh" | "bash" | "zsh" | "dash" | "ksh" => Language::Shell,
+                _ => Language::Plain,
+            };
+        }
+
+        // Pretty printed arrays open on a line of their own
+        let mut chars = text.chars().filter(|chr| !chr.is_whitespace());
+        match (chars.next(), chars.next()) {
+            (Some('{'), _) => return Language::Json,
+            (Some('['), Some('{' | '[' | '"' | ']' | '0'..='9')) => return Language::Json,
+            _ => {}
+        }
+
+        let count = |is_like: fn(&str) -> bool| lines.iter().filter(|line| is_like(line)).count();
+        let scores = [
+            (Language::Log, count(looks_like_log)),
+            (Language::Toml, count(looks_like_toml)),
+            (
+                Language::Yaml,
+                count(looks_like_yaml) + usize::from(first == "---"),
+            ),
+        ];
+
+        scores
+            .into_iter()
+            .filter(|(_, score)| *score * 2 >= lines.len())
+            .max_by_key(|(_, score)| *score)
+            .map(|(language, _)| language)
+            .unwrap_or(Language::Plain)
+    }
+
+    /// Sorted, non overlapping spans of the highlighted tokens of `text`
+    pub fn tokenize(&self, text: &str) -> Vec<Span> {
+        tokenize(text, self.grammar())
+    }
+
+    fn grammar(&self) -> &'static Grammar {
+        match self {
+            Language::Rust => &RUST,
+            Language::Toml => &TOML,
+            Language::Json => &JSON,
+            Language::Yaml => &YAML,
+            Language::Shell => &SHELL,
+            Language::Log => &LOG,
+            Language::Plain => &PLAIN,
+        }
+    }
+}
+
+fn looks_like_log(line: &str) -> bool {
+    line.starts_with(|chr: char| chr.is_ascii_digit()) && line.contains(':')
+        || line
+            .split(|chr: char| !chr.is_ascii_alphabetic())
+            .any(|word| log_level(word).is_some())
+}
+
+fn looks_like_toml(line: &str) -> bool {
+    line.starts_with('[') && line.ends_with(']')
+        || line
+            .split_once(" = ")
+            .is_some_and(|(key, _)| key.chars().all(is_key_char))
+}
+
+fn looks_like_yaml(line: &str) -> bool {
+    let line = line.strip_prefix("- ").unwrap_or(line);
+    line.split_once(':').is_some_and(|(key, value)| {
+        !key.is_empty()
+            && key.chars().all(is_key_char)
+            && (value.is_empty() || value.starts_with(' '))
+    })
+}
+
+fn is_key_char(chr: char) -> bool {
+    chr.is_ascii_alphanumeric() || matches!(chr, '_' | '-' | '.' | '"' | '\'')
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Token {
+    Text,
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Literal,
+    Type,
+    Key,
+    Section,
+    Variable,
+    Timestamp,
+    Error,
+    Warning,
+    Info,
+    Debug,
+}
+
+pub type Span = (Segment, Token);
+
+/// Just enough of a language's lexical rules to color it
+struct Grammar {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [u8],
+    multiline_strings: bool,
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    // Capitalized identifiers are types (Rust)
+    types: bool,
+    // `$name` and `${name}` (shell)
+    variables: bool,
+    // What separates a key from its value at the start of a line (toml `=`, yaml `:`)
+    key_separator: Option<u8>,
+    // `[table]` headers (toml)
+    sections: bool,
+    // Leading timestamps and ERROR/WARN/... levels (logs)
+    log_levels: bool,
+}
+
+const GRAMMAR: Grammar = Grammar {
+    line_comments: &[],
+    block_comment: None,
+    quotes: &[],
+    multiline_strings: false,
+    keywords: &[],
+    literals: &[],
+    types: false,
+    variables: false,
+    key_separator: None,
+    sections: false,
+    log_levels: false,
+};
+
+static RUST: Grammar = Grammar {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: b"\"'",
+    multiline_strings: true,
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+        "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ],
+    literals: &["true", "false"],
+    types: true,
+    ..GRAMMAR
+};
+
+static TOML: Grammar = Grammar {
+    line_comments: &["#"],
+    quotes: b"\"'",
+    literals: &["true", "false", "inf", "nan"],
+    key_separator: Some(b'='),
+    sections: true,
+    ..GRAMMAR
+};
+
+static JSON: Grammar = Grammar {
+    quotes: b"\"",
+    literals: &["true", "false", "null"],
+    ..GRAMMAR
+};
+
+static YAML: Grammar = Grammar {
+    line_comments: &["#"],
+    quotes: b"\"'",
+    literals: &["true", "false", "null", "yes", "no", "on", "off"],
+    key_separator: Some(b':'),
+    ..GRAMMAR
+};
+
+static SHELL: Grammar = Grammar {
+    line_comments: &["#"],
+    quotes: b"\"'`",
+    multiline_strings: true,
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "local", "export", "readonly", "select", "exit",
+    ],
+    literals: &["true", "false"],
+    variables: true,
+    ..GRAMMAR
+};
+
+static LOG: Grammar = Grammar {
+    quotes: b"\"",
+    log_levels: true,
+    ..GRAMMAR
+};
+
+static PLAIN: Grammar = GRAMMAR;
+
+fn log_level(word: &str) -> Option<Token> {
+    let token = match word {
+        "ERROR" | "ERR" | "FATAL" | "PANIC" | "CRITICAL" | "error" => Token::Error,
+        "WARN" | "WARNING" | "warning" => Token::Warning,
+        "INFO" | "NOTICE" => Token::Info,
+        "DEBUG" | "TRACE" => Token::Debug,
+        _ => return None,
+    };
+    Some(token)
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn find_from(bytes: &[u8], from: usize, pattern: &[u8]) -> Option<usize> {
+    bytes[from..]
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|position| from + position)
+}
+
+fn line_end(bytes: &[u8], from: usize) -> usize {
+    find_from(bytes, from, b"\n").unwrap_or(bytes.len())
+}
+
+/// Length of a `key` followed by the key separator, if the line starting at `at` has one
+fn key_len(bytes: &[u8], at: usize, separator: u8) -> Option<usize> {
+    let end = line_end(bytes, at);
+    let position = bytes[at..end].iter().position(|byte| *byte == separator)?;
+    let key = &bytes[at..at + position];
+    let is_key = !key.is_empty()
+        && key
+            .iter()
+            .all(|byte| is_key_char(*byte as char) || *byte == b' ')
+        && (separator != b':'
+            || bytes
+                .get(at + position + 1)
+                .is_none_or(|next| next.is_ascii_whitespace()));
+    is_key.then(|| key.trim_ascii_end().len())
+}
+
+/// Length of a leading log timestamp such as `2023-09-21 14:03:11.512` or `14:03:11`
+fn timestamp_len(bytes: &[u8], at: usize) -> Option<usize> {
+    let mut len = 0;
+    let mut saw_separator = false;
+    while let Some(byte) = bytes.get(at + len) {
+        match byte {
+            b'0'..=b'9' => {}
+            b'-' | b':' | b'/' | b'.' | b',' | b'T' | b'Z' | b'+' => saw_separator = true,
+            // A single space may sit between the date and the time
+            b' ' if bytes.get(at + len + 1).is_some_and(u8::is_ascii_digit) => {}
+            _ => break,
+        }
+        len += 1;
+    }
+    (saw_separator && len >= 5).then_some(len)
+}
+
+/// Split `text` into sorted, non overlapping spans of highlighted tokens
+fn tokenize(text: &str, grammar: &Grammar) -> Vec<Span> {
+    let bytes = text.as_bytes();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut at = 0;
+    let mut line_start = true;
+
+    while at < bytes.len() {
+        let byte = bytes[at];
+        let previous = at.checked_sub(1).map(|index| bytes[index]);
+        let after_word = previous.is_some_and(is_ident_byte);
+
+        if byte == b'\n' {
+            line_start = true;
+            at += 1;
+            continue;
+        }
+        if byte == b' ' || byte == b'\t' {
+            at += 1;
+            continue;
+        }
+
+        let start = at;
+        let at_line_start = std::mem::replace(&mut line_start, false);
+        let token = if let Some((open, close)) = grammar
+            .block_comment
+            .filter(|(open, _)| bytes[at..].starts_with(open.as_bytes()))
+        {
+            at = find_from(bytes, at + open.len(), close.as_bytes())
+                .map_or(bytes.len(), |end| end + close.len());
+            Token::Comment
+        } else if grammar.line_comments.iter().any(|comment| {
+            bytes[at..].starts_with(comment.as_bytes())
+                && previous.is_none_or(|previous| previous.is_ascii_whitespace())
+        }) {
+            at = line_end(bytes, at);
+            Token::Comment
+        } else if let Some(len) = (grammar.log_levels && at_line_start)
+            .then(|| timestamp_len(bytes, at))
+            .flatten()
+        {
+            at += len;
+            Token::Timestamp
+        } else if grammar.sections && at_line_start && byte == b'[' {
+            let end = line_end(bytes, at);
+            at = bytes[at..end]
+                .iter()
+                .position(|byte| *byte == b']')
+                .map_or(end, |position| at + position + 1);
+            Token::Section
+        } else if grammar.key_separator == Some(b':')
+            && at_line_start
+            && bytes[at..].starts_with(b"- ")
+        {
+            // A yaml list item may hold a key of its own
+            at += 2;
+            line_start = true;
+            continue;
+        } else if let Some(len) = grammar
+            .key_separator
+            .filter(|_| at_line_start)
+            .and_then(|separator| key_len(bytes, at, separator))
+        {
+            at += len;
+            Token::Key
+        } else if grammar.quotes.contains(&byte) && !after_word {
+            if byte == b'\'' && grammar.types && !is_char_literal(bytes, at) {
+                // A Rust lifetime, not a char
+                at += 1;
+                while at < bytes.len() && is_ident_byte(bytes[at]) {
+                    at += 1;
+                }
+                Token::Type
+            } else {
+                at = string_end(bytes, at, grammar.multiline_strings);
+                Token::String
+            }
+        } else if byte.is_ascii_digit() && !after_word {
+            while at < bytes.len() && (is_ident_byte(bytes[at]) || bytes[at] == b'.') {
+                at += 1;
+            }
+            Token::Number
+        } else if is_ident_byte(byte) && !after_word {
+            while at < bytes.len() && is_ident_byte(bytes[at]) {
+                at += 1;
+            }
+            match classify(&text[start..at], grammar) {
+                Some(token) => token,
+                None => continue,
+            }
+        } else if byte == b'$' && grammar.variables {
+            at += 1;
+            if bytes.get(at) == Some(&b'{') {
+                at = find_from(bytes, at, b"}").map_or(line_end(bytes, at), |end| end + 1);
+            } else {
+                while at < bytes.len() && (is_ident_byte(bytes[at]) || bytes[at] == b'?') {
+                    at += 1;
+                }
+            }
+            Token::Variable
+        } else {
+            // Step over a whole (possibly multi byte) character
+            at += text[at..].chars().next().map_or(1, char::len_utf8);
+            continue;
+        };
+
+        // Strings directly followed by a `:` are object keys (json)
+        let token =
+            if token == Token::String && bytes[at..].trim_ascii_start().first() == Some(&b':') {
+                Token::Key
+            } else {
+                token
+            };
+        spans.push((start..at, token));
+    }
+
+    spans
+}
+
+fn is_char_literal(bytes: &[u8], at: usize) -> bool {
+    match bytes.get(at + 1) {
+        Some(b'\\') => true,
+        Some(_) => bytes.get(at + 2) == Some(&b'\''),
+        None => false,
+    }
+}
+
+/// Index just past the quote closing the string that opens at `at`
+fn string_end(bytes: &[u8], at: usize, multiline: bool) -> usize {
+    let quote = bytes[at];
+    let mut index = at + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 1,
+            b'\n' if !multiline => return index,
+            byte if byte == quote => return index + 1,
+            _ => {}
+        }
+        index += 1;
+    }
+    bytes.len()
+}
+
+fn classify(word: &str, grammar: &Grammar) -> Option<Token> {
+    if grammar.log_levels {
+        return log_level(word);
+    }
+    if grammar.keywords.contains(&word) {
+        Some(Token::Keyword)
+    } else if grammar.literals.contains(&word) {
+        Some(Token::Literal)
+    } else if grammar.types && word.starts_with(|chr: char| chr.is_ascii_uppercase()) {
+        Some(Token::Type)
+    } else {
+        None
+    }
+}
+
+fn token_color(theme: &SyntaxTheme, token: Token, text_color: Color32) -> Color32 {
+    let rgb = |r, g, b| Color32::from_rgb(r, g, b);
+    match theme {
+        SyntaxTheme::Off => text_color,
+        SyntaxTheme::Dark => match token {
+            Token::Text => text_color,
+            Token::Comment | Token::Debug | Token::Timestamp => rgb(128, 128, 128),
+            Token::String => rgb(206, 145, 120),
+            Token::Number => rgb(181, 206, 168),
+            Token::Keyword => rgb(86, 156, 214),
+            Token::Literal => rgb(79, 193, 255),
+            Token::Type => rgb(78, 201, 176),
+            Token::Key | Token::Variable => rgb(156, 220, 254),
+            Token::Section => rgb(220, 220, 170),
+            Token::Error => rgb(244, 71, 71),
+            Token::Warning => rgb(229, 192, 123),
+            Token::Info => rgb(106, 190, 100),
+        },
+        SyntaxTheme::Light => match token {
+            Token::Text => text_color,
+            Token::Comment | Token::Debug | Token::Timestamp => rgb(110, 119, 129),
+            Token::String => rgb(10, 48, 105),
+            Token::Number | Token::Literal => rgb(5, 80, 174),
+            Token::Keyword => rgb(207, 34, 46),
+            Token::Type => rgb(149, 56, 0),
+            Token::Key | Token::Variable => rgb(17, 99, 41),
+            Token::Section => rgb(130, 80, 223),
+            Token::Error => rgb(200, 20, 20),
+            Token::Warning => rgb(154, 103, 0),
+            Token::Info => rgb(26, 127, 55),
+        },
+    }
+}
+
+/// Everything besides the file itself that changes how a layout job looks
+#[derive(PartialEq)]
+struct JobStyle {
+    text_mode: TextMode,
+    theme: SyntaxTheme,
+    font_id: FontId,
+    text_color: Color32,
+}
+
+impl JobStyle {
+    fn format(&self, token: Token) -> TextFormat {
+        TextFormat {
+            font_id: self.font_id.clone(),
+            color: token_color(&self.theme, token, self.text_color),
+            italics: token == Token::Comment,
+            ..Default::default()
+        }
+    }
+
+    fn layout_job(&self, text: &str, segment: &Segment, spans: &[Span]) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        let mut at = segment.start;
+        let first = spans.partition_point(|(range, _)| range.end <= segment.start);
+
+        for (range, token) in &spans[first..] {
+            if range.start >= segment.end {
+                break;
+            }
+            let start = range.start.max(segment.start);
+            let end = range.end.min(segment.end);
+            if at < start {
+                job.append(&text[at..start], 0.0, self.format(Token::Text));
+            }
+            job.append(&text[start..end], 0.0, self.format(*token));
+            at = end;
+        }
+        if at < segment.end {
+            job.append(&text[at..segment.end], 0.0, self.format(Token::Text));
+        }
+
+        job
+    }
+}
+
+const MAX_CACHED_STYLES: usize = 4;
+
+struct CachedFile {
+    version: u64,
+    spans: Vec<Span>,
+    // One entry per style the file is shown in, split panes may show it in several at once
+    jobs: Vec<(JobStyle, Vec<LayoutJob>)>,
+}
+
+/// Highlighted layout jobs of every file that has been displayed, kept until the file is reloaded or gone
+#[derive(Default)]
+pub struct HighlightCache {
+    cached_files: HashMap<PathBuf, CachedFile>,
+}
+
+impl HighlightCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the files that were deleted or renamed away by `updates`
+    pub fn apply(&mut self, updates: &[CacheUpdate], file_cache: &FileCache) {
+        let gone = updates
+            .iter()
+            .any(|update| matches!(update, CacheUpdate::Deleted(_) | CacheUpdate::Renamed(..)));
+        if !gone {
+            return;
+        }
+        // Cached by path on disk, a deleted directory takes everything below it along
+        self.cached_files.retain(|path, _| {
+            file_cache
+                .relative_key(path)
+                .is_some_and(|key| file_cache.contains(&key))
+        });
+    }
+
+    /// One layout job per segment of `text_mode`, or None when highlighting is turned off
+    pub fn layout_jobs(
+        &mut self,
+        file: &File,
+        text_mode: &TextMode,
+        theme: &SyntaxTheme,
+        font_id: &FontId,
+        text_color: Color32,
+    ) -> Option<&Vec<LayoutJob>> {
+        if let SyntaxTheme::Off = theme {
+            return None;
+        }
+
+        let file_form = file.file_form_ref();
+        let text = file_form.text_ref();
+        let cached = self
+            .cached_files
+            .entry(file.path_ref().clone())
+            .or_insert_with(|| CachedFile {
+                version: 0,
+                spans: Vec::new(),
+                jobs: Vec::new(),
+            });
+
+        if cached.version != file.version() {
+            let language = Language::detect(file.path_ref(), text);
+            cached.version = file.version();
+            cached.spans = language.tokenize(text);
+            cached.jobs.clear();
+        }
+
+        let style = JobStyle {
+            text_mode: *text_mode,
+            theme: *theme,
+            font_id: font_id.clone(),
+            text_color,
+        };
+        let index = match cached
+            .jobs
+            .iter()
+            .position(|(cached_style, _)| *cached_style == style)
+        {
+            Some(index) => index,
+            None => {
+                let segments = file_form.get_file_text(text_mode)?;
+                let jobs = segments
+                    .iter()
+                    .map(|segment| style.layout_job(text, segment, &cached.spans))
+                    .collect();
+                // Dragging the font size around would otherwise pile up a style per frame
+                if cached.jobs.len() >= MAX_CACHED_STYLES {
+                    cached.jobs.remove(0);
+                }
+                cached.jobs.push((style, jobs));
+                cached.jobs.len() - 1
+            }
+        };
+
+        cached.jobs.get(index).map(|(_, jobs)| jobs)
+    }
+}
@@ -1,31 +1,74 @@
 //
 
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use clap::ValueEnum;
+
+use crate::cmd_args::FollowArg;
+use crate::cmd_args::{Args, TextModeArg};
+use crate::control::{self, Control, Reply, Request};
 use crate::eframe_tools::make_rich;
 use crate::files;
+use crate::files::CacheUpdate;
 use crate::files::MasterPath;
+use crate::files::STDIN_KEY;
+use crate::json_events::JsonEvent;
+use crate::list_filters::ListFilters;
+use crate::live_watch::activity_feed::ActivityFeed;
+use crate::live_watch::cross_search::CrossSearch;
+use crate::live_watch::file_tree::FileTree;
+use crate::live_watch::filter_panel::FilterPanel;
+use crate::live_watch::follow_changes::{Follow, FollowChanges, FollowMode};
+use crate::live_watch::highlight::HighlightCache;
+use crate::live_watch::notify_panel::NotifyPanel;
+use crate::live_watch::panes::{Panes, SplitDirection};
+use crate::live_watch::quick_open::QuickOpen;
 use crate::live_watch::settings;
 use crate::live_watch::settings::TextMode;
+use crate::live_watch::tabs::Tabs;
+use crate::live_watch::trigger_panel::TriggerPanel;
+use crate::live_watch::watch_list::WatchList;
+use crate::messages::Loglet;
+use crate::notifications::{ChangeNotifier, DesktopNotifier};
+use crate::triggers;
+use crate::watching::backend::{self, WatchBackend};
+use crate::watching::watcher::{WatchEvent, WatchOptions, Watcher};
 use crate::windows::error_messages::ErrorMessage;
 use crate::windows::generic_windows::GenericWindow;
-
-use super::watcher_keep;
+use tokio::sync::mpsc::UnboundedSender;
 
 pub struct LiveWatch {
-    watch_list: Option<files::WatchList>,
+    watch_list: Option<WatchList>,
     master_path: files::MasterPath,
+    watcher: Watcher,
+    // Debounced batches of changed paths, for the triggers
+    batch_tx: UnboundedSender<Vec<PathBuf>>,
+    // What the triggers match changed paths against, kept in line with the Watch List
+    trigger_roots: triggers::Roots,
+    control: Option<Control>,
     err_msg: ErrorMessage,
     rt: Arc<Mutex<tokio::runtime::Runtime>>,
     settings: settings::Settings,
+    highlight_cache: HighlightCache,
+    panes: Panes,
+    cross_search: CrossSearch,
+    file_tree: FileTree,
+    quick_open: QuickOpen,
+    follow_changes: FollowChanges,
+    activity_feed: ActivityFeed,
+    change_notifier: ChangeNotifier,
+    notify_panel: NotifyPanel,
+    filter_panel: FilterPanel,
+    trigger_panel: TriggerPanel,
 }
 
 impl LiveWatch {
-    fn get_watch_list_mut(&mut self) -> &mut Option<files::WatchList> {
+    fn get_watch_list_mut(&mut self) -> &mut Option<WatchList> {
         &mut self.watch_list
     }
-    fn get_watch_list_ref(&self) -> &Option<files::WatchList> {
+    fn get_watch_list_ref(&self) -> &Option<WatchList> {
         &self.watch_list
     }
     fn get_master_path_ref(&self) -> &MasterPath {
@@ -40,10 +83,10 @@ impl LiveWatch {
 }
 
 impl LiveWatch {
-    fn watch_list_mut(&mut self) -> &mut Option<files::WatchList> {
+    fn watch_list_mut(&mut self) -> &mut Option<WatchList> {
         self.get_watch_list_mut()
     }
-    fn watch_list_ref(&self) -> Option<&files::WatchList> {
+    fn watch_list_ref(&self) -> Option<&WatchList> {
         self.get_watch_list_ref().as_ref()
     }
 
@@ -59,44 +102,136 @@ impl LiveWatch {
     }
 }
 
-impl Default for LiveWatch {
-    fn default() -> Self {
+impl LiveWatch {
+    /// Start watching the (absolute) paths of `args`, see [`crate::cmd_args::parse`]
+    pub fn new(args: Args) -> Self {
         let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
-        let err_msg = ErrorMessage::new();
-        let (master_path, path_buf_rx) = files::get_master_path().unwrap();
-
-        // Handle async/parrallel watcher
-        let (file_update_tx, file_update_rx) = tokio::sync::mpsc::channel(2);
-        let watcher = watcher_keep::create_watcher_with_actions(
-            file_update_tx,
+        let mut err_msg = ErrorMessage::new();
+        let activity_feed = ActivityFeed::new();
+        let trigger_panel = TriggerPanel::new();
+        let roots = args.paths.clone();
+
+        // The same rules keep ignored paths out of the Watch List and away from the watcher
+        let mut options = WatchOptions::new();
+        options.ignore_globs = args.ignore_globs.clone();
+        options.debounce = args.debounce().unwrap_or(options.debounce);
+        options.backend = WatchBackend::from(args.backend);
+        options.poll_interval = args.poll_interval().unwrap_or(options.poll_interval);
+        options.record = args.record.clone();
+        options.replay = args.replay.clone();
+        options.stdin = args.stdin;
+        if let Some(notice) = backend::notice(options.backend, &roots, options.poll_interval) {
+            err_msg.push_loglet(Loglet::new("Info", &notice, &crate::time_of_day()));
+        }
+        let watcher = Watcher::new(&roots, &options, rt.clone()).unwrap_or_else(|err| {
+            err_msg.push_err(&format!(
+                "Bad --ignore glob or session, watching without them (only the defaults and ignore files apply): {}",
+                err
+            ));
+            options.ignore_globs.clear();
+            options.record = None;
+            options.replay = None;
+            Watcher::new(&roots, &options, rt.clone()).unwrap()
+        });
+        // The first path is the one switching roots (`MasterPath::path_tx`) starts from, there is none with only `-`
+        let master_path = MasterPath::new(roots.first().cloned(), watcher.path_tx_clone());
+
+        // On-change triggers run once the tree has been quiet for a moment
+        let (batch_tx, batch_rx) = tokio::sync::mpsc::unbounded_channel();
+        let trigger_roots = Arc::new(RwLock::new(roots.clone()));
+        let run_triggers = triggers::run_triggers(
+            trigger_panel.triggers_clone(),
+            trigger_roots.clone(),
+            batch_rx,
+            trigger_panel.sender_clone(),
             err_msg.sender_clone(),
-            rt.clone(),
         );
+        crate::force_am_once(rt.clone(), |rt| {
+            rt.spawn(run_triggers);
+        });
 
-        let watch_list = Some(files::WatchList::new(
-            master_path.path_ref().as_ref().unwrap(),
+        let mut watch_list = Some(WatchList::new(
+            watcher.file_store(ListFilters::new()),
             "Watch List",
-            file_update_rx,
-            err_msg.sender_clone(),
         ));
 
-        watcher_keep::spawn_watcher_thread(
-            watcher,
-            master_path.path_clone(),
-            path_buf_rx,
-            err_msg.sender_clone(),
-            rt.clone(),
-        );
+        // Scripts, and `live_watch <path>` run again, drive this window through the socket
+        let socket = args
+            .socket
+            .clone()
+            .unwrap_or_else(control::default_socket_path);
+        let control = match Control::bind(&socket, rt.clone()) {
+            Ok(control) => Some(control),
+            Err(err) => {
+                err_msg.push_err(&format!(
+                    "No control socket, {} is taken: {}",
+                    socket.display(),
+                    err
+                ));
+                None
+            }
+        };
+
+        let desktop_notifier = DesktopNotifier::new(err_msg.sender_clone());
+        let change_notifier =
+            ChangeNotifier::new(Box::new(desktop_notifier), Duration::from_secs(10));
 
         // Settings
-        let settings = settings::Settings::default();
+        let settings = settings::Settings::new(
+            args.text_mode.map(TextMode::from),
+            args.no_wrap.then_some(false),
+            args.font_size,
+            None,
+            None,
+            Some(args.tail),
+        );
+        let mut follow_changes = FollowChanges::new();
+        if let Some(follow) = args.follow {
+            follow_changes.enable(match follow {
+                FollowArg::Switch => FollowMode::Switch,
+                FollowArg::Flash => FollowMode::Flash,
+            });
+        }
+
+        // Start out with what is piped in open, or else the first file of the Watch List
+        let mut tabs = Tabs::new();
+        if let Some(watch_list) = &mut watch_list {
+            if args.stdin {
+                watch_list
+                    .modal_machine_mut()
+                    .select(PathBuf::from(STDIN_KEY));
+            }
+            let first = watch_list.modal_machine_ref().get_selected_option();
+            if watch_list.file_cache_ref().contains(&first) {
+                if let Err(err) = watch_list.file_cache_mut().load(&first) {
+                    err_msg.push_err(&err.to_string());
+                }
+                tabs.open(first, *settings.text_mode_ref());
+            }
+        }
+        let filter_panel = FilterPanel::new(&ListFilters::new());
 
         LiveWatch {
             watch_list,
             master_path,
+            watcher,
+            batch_tx,
+            trigger_roots,
+            control,
             err_msg,
             rt,
             settings,
+            highlight_cache: HighlightCache::new(),
+            panes: Panes::new(tabs),
+            cross_search: CrossSearch::new(),
+            file_tree: FileTree::new(),
+            quick_open: QuickOpen::new(),
+            follow_changes,
+            activity_feed,
+            change_notifier,
+            notify_panel: NotifyPanel::new(),
+            filter_panel,
+            trigger_panel,
         }
     }
 }
@@ -132,109 +267,396 @@ fn user_settings(lw: &mut LiveWatch, ui: &mut egui::Ui, ctx: egui::Context) {
     egui::introspection::font_id_ui(ui, &mut lw.settings_mut().font_size_mut());
 
     ui.horizontal(|ui| {
-        let text_mode = lw.settings_mut().text_mode_mut();
-        ui.radio_value(text_mode, settings::TextMode::Standard, "Standard");
-        ui.radio_value(text_mode, settings::TextMode::Newline, "Separate Lines");
+        let before = lw
+            .panes
+            .focused_ref()
+            .tabs_ref()
+            .active_ref()
+            .map_or(*lw.settings_ref().text_mode_ref(), |tab| {
+                *tab.view_ref().text_mode_ref()
+            });
+        let mut text_mode = before;
+        ui.radio_value(&mut text_mode, settings::TextMode::Standard, "Standard");
         ui.radio_value(
-            text_mode,
+            &mut text_mode,
+            settings::TextMode::Newline,
+            "Separate Lines",
+        );
+        ui.radio_value(
+            &mut text_mode,
             settings::TextMode::AllNewline,
             "All Separate Lines",
         );
         ui.radio_value(
-            text_mode,
+            &mut text_mode,
             settings::TextMode::Selectable,
             "Highlight/Copyable Mode",
         );
+        if text_mode != before {
+            set_text_mode(lw, text_mode);
+        }
 
         let current = lw.settings_ref().word_wrap_ref().to_owned();
         if ui.radio(current, "Word Wrap").clicked() {
             lw.settings_mut().word_wrap_set(!current);
         }
     });
+
+    ui.horizontal(|ui| {
+        ui.label("Syntax Highlighting:");
+        let syntax_theme = lw.settings_mut().syntax_theme_mut();
+        ui.radio_value(syntax_theme, settings::SyntaxTheme::Off, "Off");
+        ui.radio_value(syntax_theme, settings::SyntaxTheme::Dark, "Dark");
+        ui.radio_value(syntax_theme, settings::SyntaxTheme::Light, "Light");
+    });
+
+    ui.horizontal(|ui| {
+        ui.checkbox(lw.settings_mut().line_numbers_mut(), "Line Numbers");
+        ui.checkbox(lw.settings_mut().tail_mut(), "Tail");
+        if let Some(tab) = lw.panes.focused_mut().tabs_mut().active_mut() {
+            tab.view_mut().goto_line_ui(ui);
+        }
+        if ui.button("Search All Files").clicked() {
+            lw.cross_search.toggle();
+        }
+        if ui.button("Activity").clicked() {
+            lw.activity_feed.toggle();
+        }
+        if ui.button("Notifications").clicked() {
+            lw.notify_panel.toggle();
+        }
+        if ui.button("Filters").clicked() {
+            lw.filter_panel.toggle();
+        }
+        if ui.button("Triggers").clicked() {
+            lw.trigger_panel.toggle();
+        }
+        if ui.button("Split Side By Side").clicked() {
+            lw.panes.split(SplitDirection::Horizontal);
+        }
+        if ui.button("Split Stacked").clicked() {
+            lw.panes.split(SplitDirection::Vertical);
+        }
+    });
+
+    lw.follow_changes.ui(ui);
+}
+
+/// Switches the active tab, new tabs start out in whatever mode was picked last
+fn set_text_mode(lw: &mut LiveWatch, text_mode: TextMode) {
+    *lw.settings_mut().text_mode_mut() = text_mode;
+    if let Some(tab) = lw.panes.focused_mut().tabs_mut().active_mut() {
+        *tab.view_mut().text_mode_mut() = text_mode;
+    }
+}
+
+fn search_all_files(lw: &mut LiveWatch, ctx: &egui::Context) -> Option<()> {
+    // Jumping to a hit goes through the Watch List like any other file selection
+    let watch_list = lw.watch_list.as_mut()?;
+    let (path, line) = lw.cross_search.show(ctx, watch_list.file_cache_ref())?;
+    watch_list.modal_machine_mut().select(path);
+    // Switch tabs right away so the jump lands in the tab of the hit
+    use_directory_list_mm_event(lw);
+    let tab = lw.panes.focused_mut().tabs_mut().active_mut()?;
+    tab.view_mut().jump_to_line(line);
+
+    None
+}
+
+fn quick_open(lw: &mut LiveWatch, ctx: &egui::Context) -> Option<()> {
+    // Picking a file in the popup is a file selection like any other
+    let watch_list = lw.watch_list.as_mut()?;
+    let chosen = lw.quick_open.show(ctx, watch_list.file_cache_ref())?;
+    watch_list.modal_machine_mut().select(chosen);
+
+    None
+}
+
+fn activity_feed(lw: &mut LiveWatch, ctx: &egui::Context) -> Option<()> {
+    // Opening a file from the feed is a file selection like any other
+    let watch_list = lw.watch_list.as_mut()?;
+    let clicked = lw.activity_feed.show(ctx, watch_list.file_cache_ref())?;
+    watch_list.modal_machine_mut().select(clicked);
+
+    None
+}
+
+fn notify_panel(lw: &mut LiveWatch, ctx: &egui::Context) -> Option<()> {
+    let watch_list = lw.watch_list.as_ref()?;
+    let current = lw.panes.focused_ref().tabs_ref().active_ref();
+    lw.notify_panel.show(
+        ctx,
+        &mut lw.change_notifier,
+        watch_list.file_cache_ref(),
+        current.map(|tab| tab.key_ref().as_path()),
+    );
+
+    None
+}
+
+fn filter_panel(lw: &mut LiveWatch, ctx: &egui::Context) -> Option<()> {
+    let watch_list = lw.watch_list.as_mut()?;
+    let filters = lw.filter_panel.show(ctx, watch_list.filters_ref())?;
+    watch_list.set_filters(filters);
+
+    None
+}
+
+fn notify_changes(lw: &mut LiveWatch, updates: &[CacheUpdate]) -> Option<()> {
+    let file_cache = lw.watch_list.as_ref()?.file_cache_ref();
+    for update in updates {
+        let CacheUpdate::Changed(key) = update else {
+            continue;
+        };
+        let Some(file) = file_cache.file_ref(key) else {
+            continue;
+        };
+        if let Err(err) = lw
+            .change_notifier
+            .changed(key, file.file_form_ref().text_ref())
+        {
+            lw.err_msg.push_err(&err.to_string());
+        }
+    }
+
+    None
 }
 
 fn display_directory_list(lw: &mut LiveWatch, ui: &mut egui::Ui) -> Option<()> {
-    let watch_list = lw.watch_list_mut().as_mut()?;
-    watch_list.modal_machine_mut().modal_machine(5, ui);
+    let watched_dirs = lw.watcher.watched_dirs();
+    ui.small(match watched_dirs.polled {
+        0 => format!("{} directories watched", watched_dirs.watched),
+        polled => format!(
+            "{} directories watched, {} polled",
+            watched_dirs.watched, polled
+        ),
+    });
+
+    // The tree only displays the Watch List, selecting still goes through its modal machine
+    let watch_list = lw.watch_list.as_mut()?;
+    let selected = watch_list.modal_machine_ref().get_selected_option();
+    let clicked = lw
+        .file_tree
+        .show(ui, watch_list.file_cache_ref(), &selected)?;
+    watch_list.modal_machine_mut().select(clicked);
 
     None
 }
 
 fn use_directory_list_mm_event(lw: &mut LiveWatch) -> Option<()> {
-    // On event (if an event happens), set the newly selected file as the current file
-    let watch_list = lw.watch_list_mut().as_mut()?;
+    // On event (if an event happens), show the newly selected file in its tab of the focused pane
+    let watch_list = lw.watch_list.as_mut()?;
     let selected_option = watch_list.modal_machine_mut().use_event()?;
-    watch_list
-        .file_cache_mut()
-        .current_file_set(selected_option);
+    // Files left out for their size load once they are opened
+    if let Err(err) = watch_list.file_cache_mut().load(&selected_option) {
+        lw.err_msg.push_err(&err.to_string());
+    }
+    lw.quick_open.opened(&selected_option);
+    let text_mode = *lw.settings.text_mode_ref();
+    lw.panes
+        .focused_mut()
+        .tabs_mut()
+        .open(selected_option, text_mode);
 
     None
 }
 
-fn render_option(text_mode: &TextMode) -> impl Fn(&mut egui::Ui) {
-    match text_mode {
-        TextMode::Newline => |ui: &mut egui::Ui| {
-            ui.separator();
-        },
-        TextMode::AllNewline => |ui: &mut egui::Ui| {
-            ui.separator();
-        },
-        TextMode::Selectable => |ui: &mut egui::Ui| {},
-        TextMode::Standard => |ui: &mut egui::Ui| {},
+fn display_panes(lw: &mut LiveWatch, ui: &mut egui::Ui) -> Option<()> {
+    // Switching tabs or panes is a file selection like any other
+    let watch_list = lw.watch_list.as_mut()?;
+    let selected = lw.panes.show(
+        ui,
+        watch_list.file_cache_ref(),
+        &lw.settings,
+        &mut lw.highlight_cache,
+    )?;
+    watch_list.modal_machine_mut().select(selected);
+
+    None
+}
+
+/// Hand what the watcher has to say to whoever it concerns, returns what changed in the Watch List
+fn receive_events(lw: &mut LiveWatch) -> Vec<CacheUpdate> {
+    let mut updates = Vec::new();
+    while let Some(event) = lw.watcher.try_next() {
+        match event {
+            WatchEvent::Update(update) => {
+                let Some(watch_list) = lw.watch_list.as_mut() else {
+                    continue;
+                };
+                for change in watch_list.apply(update) {
+                    if let Some(control) = &lw.control {
+                        let file_cache = watch_list.file_cache_ref();
+                        control.publish(JsonEvent::from_change(&change, file_cache, None));
+                    }
+                    updates.push(change.cache_update());
+                }
+            }
+            WatchEvent::Activity(activity) => {
+                if let Some(watch_list) = lw.watch_list.as_ref() {
+                    lw.activity_feed
+                        .receive(activity, watch_list.file_cache_ref());
+                }
+            }
+            WatchEvent::Changes(paths) => {
+                let _ = lw.batch_tx.send(paths);
+            }
+            WatchEvent::Error(loglet) => lw.err_msg.push_loglet(loglet),
+        }
     }
+
+    updates
 }
 
-fn display_file(lw: &mut LiveWatch, ui: &mut egui::Ui) {
-    let subfn = |ui: &mut egui::Ui| -> Option<()> {
-        let watch_list = lw.watch_list.as_ref()?;
-        let file_form = watch_list.file_cache_ref().full_file()?;
-        let rich_texts = file_form.get_file_text(lw.settings_ref().text_mode_ref())?;
-        let font_id = lw.settings.font_size_ref().to_owned();
+/// Watch `path` instead of the current root, starting out with its first file
+fn switch_root(lw: &mut LiveWatch, path: &Path) -> Reply {
+    let root = match path.canonicalize() {
+        Ok(root) => root,
+        Err(err) => return Reply::err(format!("can not watch '{}': {}", path.display(), err)),
+    };
+    let Some(watch_list) = lw.watch_list.as_mut() else {
+        return Reply::err("there is no Watch List");
+    };
+    if let Err(err) = lw.master_path.switch(root.clone()) {
+        return Reply::err(err);
+    }
+    for err in watch_list.switch_roots(std::slice::from_ref(&root)) {
+        lw.err_msg.push_err(&err);
+    }
+    *lw.trigger_roots.write().unwrap() = vec![root];
+    if let Some(first) = watch_list.file_cache_ref().keys().min().cloned() {
+        watch_list.modal_machine_mut().select(first);
+    }
 
-        // TODO: this is so unnecessarily expensive, but egui doesnt support selectable text properly yet... either store this somewhere as a string and rip it out or wait for egui to do its thing...
-        if let TextMode::Selectable = lw.settings_ref().text_mode_ref() {
-            let mut text: String = rich_texts.iter().map(|rt| rt.text().to_string()).collect();
-            ui.add(egui::TextEdit::multiline(&mut text));
-            return None;
-        }
+    Reply::ok()
+}
+
+/// What `live_watch <path>` does to a window that is already open
+fn open_path(lw: &mut LiveWatch, path: &Path) -> Reply {
+    let Some(watch_list) = lw.watch_list.as_mut() else {
+        return Reply::err("there is no Watch List");
+    };
+    let file_cache = watch_list.file_cache_ref();
+    // Already watched
+    let canonical = path.canonicalize().ok();
+    if file_cache
+        .roots_ref()
+        .iter()
+        .any(|root| Some(root) == canonical.as_ref())
+    {
+        return Reply::ok();
+    }
+    // A file below the roots is picked, like clicking it in the list
+    if let Some(key) = file_cache
+        .relative_key(path)
+        .filter(|key| file_cache.contains(key))
+    {
+        watch_list.modal_machine_mut().select(key);
+        return Reply::ok();
+    }
+
+    switch_root(lw, path)
+}
 
-        // println!("file text");
-        for rt in rich_texts {
-            // println!("<{}>", rt.text());
-            ui.add(egui::Label::new(rt.clone().font(font_id.clone())));
-            render_option(lw.settings_ref().text_mode_ref())(ui);
+fn select_key(lw: &mut LiveWatch, key: &Path) -> Reply {
+    match lw.watch_list.as_mut() {
+        Some(watch_list) if watch_list.file_cache_ref().contains(key) => {
+            watch_list.modal_machine_mut().select(key.to_path_buf());
+            Reply::ok()
         }
-        // println!("/n");
+        _ => Reply::err(format!("{} is not in the Watch List", key.display())),
+    }
+}
 
-        None
+/// The file open in the focused pane
+fn current(lw: &LiveWatch) -> Reply {
+    let (Some(watch_list), Some(tab)) = (
+        lw.watch_list_ref(),
+        lw.panes.focused_ref().tabs_ref().active_ref(),
+    ) else {
+        return Reply::err("no file is open");
     };
+    let path = watch_list.file_cache_ref().absolute_path(tab.key_ref());
+    let text_mode = TextModeArg::from(*tab.view_ref().text_mode_ref());
+
+    Reply {
+        key: Some(tab.key_ref().to_string_lossy().into_owned()),
+        path: Some(path.to_string_lossy().into_owned()),
+        text_mode: text_mode
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned()),
+        ..Reply::ok()
+    }
+}
 
-    egui::ScrollArea::both().show(ui, |ui| {
-        subfn(ui);
-        ui.allocate_space(ui.available_size());
-    });
+/// Answer whatever scripts asked for through the control socket
+fn control_requests(lw: &mut LiveWatch) {
+    while let Some(pending) = lw.control.as_mut().and_then(Control::try_next) {
+        let reply = match pending.request_ref() {
+            Request::Open { path } => open_path(lw, path),
+            Request::SwitchRoot { path } => switch_root(lw, path),
+            Request::Select { key } => select_key(lw, key),
+            Request::SetTextMode { mode } => match TextModeArg::from_str(mode, true) {
+                Ok(text_mode) => {
+                    set_text_mode(lw, TextMode::from(text_mode));
+                    Reply::ok()
+                }
+                Err(err) => Reply::err(err),
+            },
+            Request::Current => current(lw),
+            // Taken care of by the socket itself
+            Request::Subscribe => Reply::ok(),
+        };
+        pending.reply(reply);
+    }
 }
 
 impl eframe::App for LiveWatch {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // First, so selections made through the socket are shown this frame
+        control_requests(self);
+
+        egui::SidePanel::left("file_tree")
+            .resizable(true)
+            .show(ctx, |ui| {
+                display_directory_list(self, ui);
+            });
+
         egui::CentralPanel::default().show(ctx, |ui| {
             header(self, ui);
             user_settings(self, ui, ctx.clone());
+            search_all_files(self, ctx);
+            quick_open(self, ctx);
+            activity_feed(self, ctx);
+            notify_panel(self, ctx);
+            filter_panel(self, ctx);
+            self.trigger_panel.show(ctx);
 
-            display_directory_list(self, ui);
-            use_directory_list_mm_event(self);
             ui.separator();
+            display_panes(self, ui);
 
-            display_file(self, ui);
+            use_directory_list_mm_event(self);
         });
 
         self.err_msg.block_update_log();
-        let maybe_mp_pb = self.master_path.path_clone();
-        if let Some(mp_pb) = maybe_mp_pb {
-            self.watch_list_mut()
-                .as_mut()
-                .unwrap()
-                .handle_updates(mp_pb);
+        let updates = receive_events(self);
+        self.panes.apply(&updates);
+        if let Some(watch_list) = &self.watch_list {
+            let file_cache = watch_list.file_cache_ref();
+            self.highlight_cache.apply(&updates, file_cache);
+        }
+        notify_changes(self, &updates);
+
+        // Following a change is a file selection like any other
+        for follow in self.follow_changes.follow(&updates) {
+            match follow {
+                Follow::Switch(key) => {
+                    if let Some(watch_list) = self.watch_list_mut() {
+                        watch_list.modal_machine_mut().select(key);
+                    }
+                }
+                Follow::Flash(key) => self.file_tree.flash(key),
+            }
         }
 
         ctx.request_repaint();
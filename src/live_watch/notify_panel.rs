@@ -0,0 +1,120 @@
+//! Window for picking the files that raise desktop notifications
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use egui::Context;
+
+use crate::files::FileCache;
+use crate::notifications::ChangeNotifier;
+
+pub struct NotifyPanel {
+    open: bool,
+    patterns: String,
+    error: Option<String>,
+}
+
+impl NotifyPanel {
+    pub fn new() -> Self {
+        NotifyPanel {
+            open: false,
+            patterns: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    fn apply_patterns(&mut self, notifier: &mut ChangeNotifier, file_cache: &FileCache) {
+        self.error = notifier
+            .set_patterns(&self.patterns)
+            .err()
+            .map(|err| err.to_string());
+        for (key, file) in file_cache.files() {
+            notifier.seed(key, file.file_form_ref().text_ref());
+        }
+    }
+
+    /// Shows the window (while open), `current` is the file the user is looking at
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        notifier: &mut ChangeNotifier,
+        file_cache: &FileCache,
+        current: Option<&Path>,
+    ) {
+        let mut open = self.open;
+        egui::Window::new("Notifications")
+            .resizable(true)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(current) = current {
+                    let name = current.file_name().unwrap_or_default().to_string_lossy();
+                    let watched = notifier.files().any(|key| key == current);
+                    match watched {
+                        true => {
+                            if ui.button(format!("Stop Notifying On {}", name)).clicked() {
+                                notifier.unwatch_file(current);
+                            }
+                        }
+                        false => {
+                            if ui.button(format!("Notify On {}", name)).clicked() {
+                                let text = file_cache
+                                    .file_ref(current)
+                                    .map(|file| file.file_form_ref().text_ref());
+                                notifier.watch_file(current.to_path_buf(), text);
+                            }
+                        }
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Also Notify On:");
+                    let edit = egui::TextEdit::singleline(&mut self.patterns)
+                        .hint_text("*.log, config/*.toml")
+                        .desired_width(200.0);
+                    if ui.add(edit).changed() {
+                        self.apply_patterns(notifier, file_cache);
+                    }
+                });
+                if let Some(err) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("At most once per file every");
+                    let mut seconds = notifier.min_interval().as_secs_f32();
+                    let drag = egui::DragValue::new(&mut seconds)
+                        .clamp_range(0.0..=600.0)
+                        .speed(0.5)
+                        .suffix("s");
+                    if ui.add(drag).changed() {
+                        notifier.set_min_interval(Duration::from_secs_f32(seconds));
+                    }
+                });
+                ui.separator();
+
+                let mut unwatch: Option<PathBuf> = None;
+                for key in notifier.files() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✖").clicked() {
+                            unwatch = Some(key.clone());
+                        }
+                        ui.label(key.display().to_string());
+                    });
+                }
+                if let Some(key) = unwatch {
+                    notifier.unwatch_file(&key);
+                }
+            });
+        self.open = open;
+    }
+}
+
+impl Default for NotifyPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
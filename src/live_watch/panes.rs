@@ -0,0 +1,370 @@
+//! Split panes, every pane shows its own tabs with its own settings overrides
+
+use std::path::PathBuf;
+
+use egui::Ui;
+
+use crate::files::{CacheUpdate, FileCache};
+use crate::live_watch::file_view;
+use crate::live_watch::highlight::HighlightCache;
+use crate::live_watch::settings::{Settings, SettingsOverrides, SyntaxTheme};
+use crate::live_watch::tabs::Tabs;
+
+/// How the panes share the central panel
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SplitDirection {
+    /// Side by side
+    Horizontal,
+    /// Stacked on top of each other
+    Vertical,
+}
+
+pub struct Pane {
+    tabs: Tabs,
+    overrides: SettingsOverrides,
+}
+
+impl Pane {
+    fn new(tabs: Tabs) -> Self {
+        Pane {
+            tabs,
+            overrides: SettingsOverrides::default(),
+        }
+    }
+
+    pub fn tabs_ref(&self) -> &Tabs {
+        &self.tabs
+    }
+
+    pub fn tabs_mut(&mut self) -> &mut Tabs {
+        &mut self.tabs
+    }
+}
+
+// What happened inside a pane this frame
+#[derive(Default)]
+struct PaneResponse {
+    select: Option<PathBuf>,
+    interacted: bool,
+    close: bool,
+}
+
+/// How the panes are laid out, every split holds its children in a direction of its own
+///
+/// The panes are numbered in the order the tree shows them, so a new pane always takes the number after the pane it
+/// was split from.
+enum Layout {
+    Pane(usize),
+    Split(SplitDirection, Vec<Layout>),
+}
+
+impl Layout {
+    fn renumber(&mut self, renumber: &impl Fn(usize) -> usize) {
+        match self {
+            Layout::Pane(index) => *index = renumber(*index),
+            Layout::Split(_, children) => {
+                for child in children {
+                    child.renumber(renumber);
+                }
+            }
+        }
+    }
+
+    /// Put the new pane `at + 1` next to pane `at`
+    fn split(&mut self, at: usize, direction: SplitDirection) {
+        self.renumber(&|index| if index > at { index + 1 } else { index });
+        self.insert(at, direction);
+    }
+
+    fn insert(&mut self, at: usize, direction: SplitDirection) -> bool {
+        match self {
+            Layout::Pane(index) if *index == at => {
+                let split = vec![Layout::Pane(at), Layout::Pane(at + 1)];
+                *self = Layout::Split(direction, split);
+                true
+            }
+            Layout::Pane(_) => false,
+            Layout::Split(split_direction, children) => {
+                // A split going the same way just gets one more child
+                let position = children
+                    .iter()
+                    .position(|child| matches!(child, Layout::Pane(index) if *index == at));
+                match position {
+                    Some(position) if *split_direction == direction => {
+                        children.insert(position + 1, Layout::Pane(at + 1));
+                        true
+                    }
+                    _ => children.iter_mut().any(|child| child.insert(at, direction)),
+                }
+            }
+        }
+    }
+
+    /// Take pane `at` out, splits left holding a single child give way to it
+    fn remove(&mut self, at: usize) {
+        if let Layout::Split(_, children) = self {
+            children.retain(|child| !matches!(child, Layout::Pane(index) if *index == at));
+            for child in children.iter_mut() {
+                child.remove(at);
+            }
+            if children.len() == 1 {
+                let only = children.pop().unwrap();
+                *self = only;
+            }
+        }
+    }
+
+    fn show(&self, ui: &mut Ui, show_pane: &mut dyn FnMut(usize, &mut Ui)) {
+        match self {
+            Layout::Pane(index) => show_pane(*index, ui),
+            Layout::Split(SplitDirection::Horizontal, children) => {
+                ui.columns(children.len(), |columns| {
+                    for (child, ui) in children.iter().zip(columns) {
+                        child.show(ui, show_pane);
+                    }
+                })
+            }
+            Layout::Split(SplitDirection::Vertical, children) => {
+                let count = children.len();
+                let spacing = ui.spacing().item_spacing.y;
+                let height = (ui.available_height() - spacing * (count - 1) as f32) / count as f32;
+                for child in children {
+                    let size = egui::vec2(ui.available_width(), height);
+                    ui.allocate_ui(size, |ui| {
+                        ui.set_min_size(size);
+                        child.show(ui, show_pane);
+                    });
+                }
+            }
+        }
+    }
+}
+
+pub struct Panes {
+    panes: Vec<Pane>,
+    focused: usize,
+    layout: Layout,
+}
+
+impl Panes {
+    pub fn new(tabs: Tabs) -> Self {
+        Panes {
+            panes: vec![Pane::new(tabs)],
+            focused: 0,
+            layout: Layout::Pane(0),
+        }
+    }
+
+    /// The pane that file selections and the global controls act on
+    pub fn focused_ref(&self) -> &Pane {
+        &self.panes[self.focused]
+    }
+
+    pub fn focused_mut(&mut self) -> &mut Pane {
+        &mut self.panes[self.focused]
+    }
+
+    /// Add a pane next to the focused one, showing the same file
+    pub fn split(&mut self, direction: SplitDirection) {
+        let mut tabs = Tabs::new();
+        if let Some(tab) = self.focused_ref().tabs_ref().active_ref() {
+            tabs.open(tab.key_ref().clone(), *tab.view_ref().text_mode_ref());
+        }
+        let mut pane = Pane::new(tabs);
+        pane.overrides = self.focused_ref().overrides.clone();
+
+        self.layout.split(self.focused, direction);
+        self.focused += 1;
+        self.panes.insert(self.focused, pane);
+    }
+
+    fn close(&mut self, index: usize) {
+        if self.panes.len() == 1 {
+            return;
+        }
+        self.panes.remove(index);
+        self.layout.remove(index);
+        self.layout
+            .renumber(&|other| if other > index { other - 1 } else { other });
+        if self.focused >= index && self.focused > 0 {
+            self.focused -= 1;
+        }
+    }
+
+    pub fn apply(&mut self, updates: &[CacheUpdate]) {
+        for pane in &mut self.panes {
+            pane.tabs.apply(updates);
+        }
+    }
+
+    /// Shows every pane, returns the key of a file that should become the selected file
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        file_cache: &FileCache,
+        settings: &Settings,
+        highlight_cache: &mut HighlightCache,
+    ) -> Option<PathBuf> {
+        let count = self.panes.len();
+        let focused = self.focused;
+        let mut responses: Vec<PaneResponse> = Vec::with_capacity(count);
+        responses.resize_with(count, PaneResponse::default);
+        let panes = &mut self.panes;
+        let mut show = |index: usize, ui: &mut Ui| {
+            responses[index] = ui
+                .push_id(("pane", index), |ui| {
+                    show_pane(
+                        &mut panes[index],
+                        index == focused,
+                        count > 1,
+                        ui,
+                        file_cache,
+                        settings,
+                        highlight_cache,
+                    )
+                })
+                .inner;
+        };
+        self.layout.show(ui, &mut show);
+
+        let mut select = None;
+        let mut close = None;
+        for (index, response) in responses.into_iter().enumerate() {
+            if response.interacted && index != self.focused {
+                self.focused = index;
+                // Keep the Watch List selection on whatever the focused pane shows
+                select = self.panes[index]
+                    .tabs
+                    .active_ref()
+                    .map(|tab| tab.key_ref().clone());
+            }
+            if response.select.is_some() {
+                self.focused = index;
+                select = response.select;
+            }
+            if response.close {
+                close = Some(index);
+            }
+        }
+        if let Some(index) = close {
+            self.close(index);
+            select = self
+                .focused_ref()
+                .tabs_ref()
+                .active_ref()
+                .map(|tab| tab.key_ref().clone());
+        }
+
+        select
+    }
+}
+
+fn override_ui<T>(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<T>,
+    global: T,
+    edit: impl FnOnce(&mut Ui, &mut T),
+) {
+    let mut overridden = value.is_some();
+    if ui.checkbox(&mut overridden, label).changed() {
+        *value = overridden.then_some(global);
+    }
+    if let Some(value) = value {
+        edit(ui, value);
+    }
+}
+
+fn overrides_ui(overrides: &mut SettingsOverrides, settings: &Settings, ui: &mut Ui) {
+    ui.horizontal_wrapped(|ui| {
+        override_ui(
+            ui,
+            "Own Font Size",
+            overrides.font_size_mut(),
+            settings.font_size_ref().clone(),
+            |ui, font_id| {
+                ui.add(egui::DragValue::new(&mut font_id.size).clamp_range(4.0..=100.0));
+            },
+        );
+        override_ui(
+            ui,
+            "Own Word Wrap",
+            overrides.word_wrap_mut(),
+            *settings.word_wrap_ref(),
+            |ui, word_wrap| {
+                ui.checkbox(word_wrap, "Wrap");
+            },
+        );
+        override_ui(
+            ui,
+            "Own Line Numbers",
+            overrides.line_numbers_mut(),
+            *settings.line_numbers_ref(),
+            |ui, line_numbers| {
+                ui.checkbox(line_numbers, "Numbers");
+            },
+        );
+        override_ui(
+            ui,
+            "Own Theme",
+            overrides.syntax_theme_mut(),
+            *settings.syntax_theme_ref(),
+            |ui, syntax_theme| {
+                ui.radio_value(syntax_theme, SyntaxTheme::Off, "Off");
+                ui.radio_value(syntax_theme, SyntaxTheme::Dark, "Dark");
+                ui.radio_value(syntax_theme, SyntaxTheme::Light, "Light");
+            },
+        );
+    });
+}
+
+fn show_pane(
+    pane: &mut Pane,
+    focused: bool,
+    closable: bool,
+    ui: &mut Ui,
+    file_cache: &FileCache,
+    settings: &Settings,
+    highlight_cache: &mut HighlightCache,
+) -> PaneResponse {
+    let mut response = PaneResponse::default();
+    let stroke = match focused {
+        true => ui.visuals().selection.stroke,
+        false => ui.visuals().widgets.noninteractive.bg_stroke,
+    };
+
+    let frame = egui::Frame::none()
+        .stroke(stroke)
+        .inner_margin(4.0)
+        .show(ui, |ui| {
+            ui.set_min_size(ui.available_size());
+            ui.horizontal(|ui| {
+                egui::CollapsingHeader::new("Pane Settings")
+                    .id_source("pane_settings")
+                    .show(ui, |ui| overrides_ui(&mut pane.overrides, settings, ui));
+                if closable && ui.small_button("Close Pane").clicked() {
+                    response.close = true;
+                }
+            });
+
+            response.select = pane.tabs.show(ui, file_cache);
+            ui.separator();
+
+            let settings = pane.overrides.apply(settings);
+            if let Some(tab) = pane.tabs.active_mut() {
+                let file = file_cache.file_ref(tab.key_ref());
+                file_view::display_file(
+                    file,
+                    &settings,
+                    highlight_cache,
+                    tab.view_mut(),
+                    focused,
+                    ui,
+                );
+            }
+        });
+
+    response.interacted =
+        ui.rect_contains_pointer(frame.response.rect) && ui.input(|i| i.pointer.any_pressed());
+    response
+}
@@ -0,0 +1,81 @@
+//! Glob patterns the user types to pick out files by their relative path
+
+use std::path::Path;
+
+use egui::Ui;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::MagicError;
+
+/// Comma or space separated globs, compiled into a GlobSet whenever they are edited
+pub struct PathFilter {
+    patterns: String,
+    compiled: Option<GlobSet>,
+    error: Option<String>,
+}
+
+impl PathFilter {
+    pub fn new() -> Self {
+        PathFilter {
+            patterns: String::new(),
+            compiled: None,
+            error: None,
+        }
+    }
+
+    fn compile(patterns: &str) -> Result<Option<GlobSet>, MagicError> {
+        let mut builder = GlobSetBuilder::new();
+        let mut any = false;
+        for pattern in patterns.split([',', ' ']).filter(|p| !p.is_empty()) {
+            builder.add(Glob::new(pattern)?);
+            any = true;
+        }
+        match any {
+            true => Ok(Some(builder.build()?)),
+            false => Ok(None),
+        }
+    }
+
+    fn recompile(&mut self) {
+        match Self::compile(&self.patterns) {
+            Ok(compiled) => {
+                self.compiled = compiled;
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    /// True when some pattern matches `path`, false for an empty filter
+    pub fn matches(&self, path: &Path) -> bool {
+        self.compiled
+            .as_ref()
+            .is_some_and(|compiled| compiled.is_match(path))
+    }
+
+    /// True when some pattern matches `path`, an empty filter allows everything
+    pub fn allows(&self, path: &Path) -> bool {
+        self.compiled
+            .as_ref()
+            .is_none_or(|compiled| compiled.is_match(path))
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, label: &str, hint: &str) {
+        ui.label(label);
+        let edit = egui::TextEdit::singleline(&mut self.patterns)
+            .hint_text(hint)
+            .desired_width(160.0);
+        if ui.add(edit).changed() {
+            self.recompile();
+        }
+        if let Some(err) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+}
+
+impl Default for PathFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
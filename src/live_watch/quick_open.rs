@@ -0,0 +1,161 @@
+//! Ctrl+P quick open: fuzzy find a file of the watched tree by its relative path
+
+use std::path::{Path, PathBuf};
+
+use egui::{Context, Key, Modifiers};
+
+use crate::files::FileCache;
+use crate::search::fuzzy_score;
+
+const MAX_RESULTS: usize = 50;
+const MAX_RECENT: usize = 20;
+// Worth a handful of well placed characters, so recency breaks near ties without burying better matches
+const RECENCY_BONUS: i64 = 40;
+
+pub struct QuickOpen {
+    open: bool,
+    request_focus: bool,
+    query: String,
+    selected: usize,
+    // Most recently opened first
+    recent: Vec<PathBuf>,
+}
+
+impl QuickOpen {
+    pub fn new() -> Self {
+        QuickOpen {
+            open: false,
+            request_focus: false,
+            query: String::new(),
+            selected: 0,
+            recent: Vec::new(),
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.request_focus = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Remember that the user looked at `key`, however they got there
+    pub fn opened(&mut self, key: &Path) {
+        if self.recent.first().is_some_and(|recent| recent == key) {
+            return;
+        }
+        self.recent.retain(|recent| recent != key);
+        self.recent.insert(0, key.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    fn recency_bonus(&self, key: &Path) -> i64 {
+        match self.recent.iter().position(|recent| recent == key) {
+            Some(rank) => RECENCY_BONUS * (MAX_RECENT - rank) as i64 / MAX_RECENT as i64,
+            None => 0,
+        }
+    }
+
+    fn ranked<'a>(&self, file_cache: &'a FileCache) -> Vec<&'a PathBuf> {
+        let mut scored: Vec<(i64, &PathBuf)> = file_cache
+            .keys()
+            .filter_map(|key| {
+                let score = fuzzy_score(&self.query, &key.to_string_lossy())?;
+                Some((score + self.recency_bonus(key), key))
+            })
+            .collect();
+        scored.sort_by(|(score, key), (other_score, other_key)| {
+            other_score.cmp(score).then_with(|| key.cmp(other_key))
+        });
+
+        scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    /// Shows the popup (while open), returns the key of the file the user picked
+    pub fn show(&mut self, ctx: &Context, file_cache: &FileCache) -> Option<PathBuf> {
+        if ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::P)) {
+            self.open();
+        }
+        if !self.open {
+            return None;
+        }
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Escape),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+
+        let results = self.ranked(file_cache);
+        if down {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(results.len().saturating_sub(1));
+
+        let mut chosen = None;
+        if enter {
+            chosen = results.get(self.selected).map(|key| (*key).clone());
+        }
+
+        egui::Window::new("Quick Open")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Go to file...")
+                        .desired_width(500.0),
+                );
+                if std::mem::take(&mut self.request_focus) {
+                    response.request_focus();
+                }
+                if response.changed() {
+                    self.selected = 0;
+                }
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for (index, key) in results.iter().enumerate() {
+                            let label = ui.selectable_label(
+                                index == self.selected,
+                                key.to_string_lossy().into_owned(),
+                            );
+                            if index == self.selected && (up || down) {
+                                label.scroll_to_me(None);
+                            }
+                            if label.clicked() {
+                                chosen = Some((*key).clone());
+                            }
+                        }
+                    });
+            });
+
+        if chosen.is_some() {
+            self.open = false;
+        }
+        chosen
+    }
+}
+
+impl Default for QuickOpen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,202 @@
+//! The Ctrl+F search bar over the displayed file
+
+use std::ops::Range;
+
+use egui::Ui;
+
+use crate::files::File;
+use crate::search::{Matcher, SearchMode};
+
+// What the current matches were computed from
+#[derive(PartialEq)]
+struct Searched {
+    path: std::path::PathBuf,
+    version: u64,
+    query: String,
+    mode: SearchMode,
+}
+
+pub struct SearchBar {
+    open: bool,
+    request_focus: bool,
+    query: String,
+    mode: SearchMode,
+    matches: Vec<Range<usize>>,
+    current: Option<usize>,
+    error: Option<String>,
+    searched: Option<Searched>,
+}
+
+impl SearchBar {
+    pub fn new() -> Self {
+        SearchBar {
+            open: false,
+            request_focus: false,
+            query: String::new(),
+            mode: SearchMode::Plain,
+            matches: Vec::new(),
+            current: None,
+            error: None,
+            searched: None,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.request_focus = true;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Every match in the displayed file, empty while the bar is closed
+    pub fn matches(&self) -> &[Range<usize>] {
+        match self.open {
+            true => &self.matches,
+            false => &[],
+        }
+    }
+
+    pub fn current_match(&self) -> Option<&Range<usize>> {
+        self.current
+            .filter(|_| self.open)
+            .and_then(|index| self.matches.get(index))
+    }
+
+    /// Recompute the matches when the query or the file changed, returns true if they were
+    fn refresh(&mut self, file: &File) -> bool {
+        let searched = Searched {
+            path: file.path_ref().clone(),
+            version: file.version(),
+            query: self.query.clone(),
+            mode: self.mode,
+        };
+        if self.searched.as_ref() == Some(&searched) {
+            return false;
+        }
+
+        // Stay on (or just after) the match the user was looking at when only the file changed
+        let same_query = self.searched.as_ref().is_some_and(|old| {
+            old.path == searched.path && old.query == searched.query && old.mode == searched.mode
+        });
+        let anchor = self
+            .current_match()
+            .map(|current| current.start)
+            .filter(|_| same_query)
+            .unwrap_or(0);
+
+        self.error = None;
+        self.matches = if self.query.is_empty() {
+            Vec::new()
+        } else {
+            match Matcher::new(&self.query, &self.mode) {
+                Ok(matcher) => matcher.find_all(file.file_form_ref().text_ref()),
+                Err(err) => {
+                    self.error = Some(err.to_string());
+                    Vec::new()
+                }
+            }
+        };
+        self.current = match self.matches.is_empty() {
+            true => None,
+            false => Some(
+                self.matches
+                    .partition_point(|found| found.start < anchor)
+                    .min(self.matches.len() - 1),
+            ),
+        };
+        self.searched = Some(searched);
+
+        !same_query
+    }
+
+    fn step(&mut self, forward: bool) -> Option<usize> {
+        let len = self.matches.len();
+        let current = self.current?;
+        let next = match forward {
+            true => (current + 1) % len,
+            false => (current + len - 1) % len,
+        };
+        self.current = Some(next);
+        self.current_match().map(|found| found.start)
+    }
+
+    pub fn next_match(&mut self) -> Option<usize> {
+        self.step(true)
+    }
+
+    pub fn previous_match(&mut self) -> Option<usize> {
+        self.step(false)
+    }
+
+    /// Shows the bar (while open), returns the byte offset of a match that should be scrolled to
+    ///
+    /// Ctrl+F only opens the bar of the `focused` view
+    pub fn show(&mut self, ui: &mut Ui, file: Option<&File>, focused: bool) -> Option<usize> {
+        if focused && ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            self.open();
+        }
+        if !self.open {
+            return None;
+        }
+
+        let mut scroll_to = None;
+        ui.horizontal(|ui| {
+            ui.label("Find:");
+            let response = ui.add(egui::TextEdit::singleline(&mut self.query).desired_width(200.0));
+            if std::mem::take(&mut self.request_focus) {
+                response.request_focus();
+            }
+
+            // Enter keeps focus on the query so the user can keep hitting it
+            let entered = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if entered {
+                response.request_focus();
+            }
+            let shift = ui.input(|i| i.modifiers.shift);
+            let escaped = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Escape));
+
+            ui.radio_value(&mut self.mode, SearchMode::Plain, "Plain");
+            ui.radio_value(&mut self.mode, SearchMode::CaseInsensitive, "Ignore Case");
+            ui.radio_value(&mut self.mode, SearchMode::Regex, "Regex");
+
+            if let Some(file) = file {
+                if self.refresh(file) {
+                    scroll_to = self.current_match().map(|found| found.start);
+                }
+            }
+
+            if ui.button("⬆").clicked() || entered && shift {
+                scroll_to = self.previous_match();
+            }
+            if ui.button("⬇").clicked() || entered && !shift {
+                scroll_to = self.next_match();
+            }
+
+            match (&self.error, self.current) {
+                (Some(err), _) => ui.colored_label(ui.visuals().error_fg_color, err),
+                (None, Some(current)) => {
+                    ui.label(format!("{}/{}", current + 1, self.matches.len()))
+                }
+                (None, None) => ui.label("No matches"),
+            };
+
+            if ui.button("✖").clicked() || escaped {
+                self.close();
+            }
+        });
+
+        scroll_to
+    }
+}
+
+impl Default for SearchBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
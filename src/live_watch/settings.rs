@@ -1,17 +1,24 @@
 use egui::FontId;
 
-#[derive(PartialEq)]
-pub enum TextMode {
-    Newline,
-    AllNewline,
-    Selectable,
-    Standard,
+pub use crate::files::TextMode;
+
+/// Colors used for syntax highlighting, `Off` shows files as plain text
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SyntaxTheme {
+    Off,
+    Dark,
+    Light,
 }
 
+#[derive(Clone)]
 pub struct Settings {
     text_mode: TextMode,
     word_wrap: bool,
     font_size: FontId,
+    syntax_theme: SyntaxTheme,
+    line_numbers: bool,
+    // Keep views scrolled to the end of their file
+    tail: bool,
 }
 
 impl Settings {
@@ -19,21 +26,30 @@ impl Settings {
         text_mode: Option<TextMode>,
         word_wrap: Option<bool>,
         font_size: Option<f32>,
+        syntax_theme: Option<SyntaxTheme>,
+        line_numbers: Option<bool>,
+        tail: Option<bool>,
     ) -> Self {
         let text_mode = text_mode.unwrap_or(TextMode::Standard);
         let word_wrap = word_wrap.unwrap_or(true);
         let font_size = font_size
             .and_then(|f| Some(FontId::proportional(f)))
             .unwrap_or(FontId::proportional(30.0));
+        let syntax_theme = syntax_theme.unwrap_or(SyntaxTheme::Dark);
+        let line_numbers = line_numbers.unwrap_or(false);
+        let tail = tail.unwrap_or(false);
 
         Settings {
             text_mode,
             word_wrap,
             font_size,
+            syntax_theme,
+            line_numbers,
+            tail,
         }
     }
     pub fn default() -> Self {
-        Self::new(None, None, None)
+        Self::new(None, None, None, None, None, None)
     }
 }
 
@@ -56,6 +72,24 @@ impl Settings {
     fn get_font_size_mut(&mut self) -> &mut FontId {
         &mut self.font_size
     }
+    fn get_syntax_theme_ref(&self) -> &SyntaxTheme {
+        &self.syntax_theme
+    }
+    fn get_syntax_theme_mut(&mut self) -> &mut SyntaxTheme {
+        &mut self.syntax_theme
+    }
+    fn get_line_numbers_ref(&self) -> &bool {
+        &self.line_numbers
+    }
+    fn get_line_numbers_mut(&mut self) -> &mut bool {
+        &mut self.line_numbers
+    }
+    fn get_tail_ref(&self) -> &bool {
+        &self.tail
+    }
+    fn get_tail_mut(&mut self) -> &mut bool {
+        &mut self.tail
+    }
 
     pub fn text_mode_mut(&mut self) -> &mut TextMode {
         self.get_text_mode_mut()
@@ -85,4 +119,64 @@ impl Settings {
     pub fn font_size_ref(&self) -> &FontId {
         self.get_font_size_ref()
     }
+    pub fn syntax_theme_ref(&self) -> &SyntaxTheme {
+        self.get_syntax_theme_ref()
+    }
+    pub fn syntax_theme_mut(&mut self) -> &mut SyntaxTheme {
+        self.get_syntax_theme_mut()
+    }
+    pub fn line_numbers_ref(&self) -> &bool {
+        self.get_line_numbers_ref()
+    }
+    pub fn line_numbers_mut(&mut self) -> &mut bool {
+        self.get_line_numbers_mut()
+    }
+    pub fn tail_ref(&self) -> &bool {
+        self.get_tail_ref()
+    }
+    pub fn tail_mut(&mut self) -> &mut bool {
+        self.get_tail_mut()
+    }
+}
+
+/// Per pane replacements for the global [`Settings`], `None` keeps the global value
+#[derive(Default, Clone)]
+pub struct SettingsOverrides {
+    font_size: Option<FontId>,
+    word_wrap: Option<bool>,
+    line_numbers: Option<bool>,
+    syntax_theme: Option<SyntaxTheme>,
+}
+
+impl SettingsOverrides {
+    pub fn font_size_mut(&mut self) -> &mut Option<FontId> {
+        &mut self.font_size
+    }
+    pub fn word_wrap_mut(&mut self) -> &mut Option<bool> {
+        &mut self.word_wrap
+    }
+    pub fn line_numbers_mut(&mut self) -> &mut Option<bool> {
+        &mut self.line_numbers
+    }
+    pub fn syntax_theme_mut(&mut self) -> &mut Option<SyntaxTheme> {
+        &mut self.syntax_theme
+    }
+
+    /// The global settings with these overrides applied on top
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        let mut settings = settings.clone();
+        if let Some(font_size) = &self.font_size {
+            settings.font_size = font_size.clone();
+        }
+        if let Some(word_wrap) = self.word_wrap {
+            settings.word_wrap = word_wrap;
+        }
+        if let Some(line_numbers) = self.line_numbers {
+            settings.line_numbers = line_numbers;
+        }
+        if let Some(syntax_theme) = self.syntax_theme {
+            settings.syntax_theme = syntax_theme;
+        }
+        settings
+    }
 }
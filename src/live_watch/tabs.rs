@@ -0,0 +1,150 @@
+//! Tab bar of the open files, every tab keeps its own view (scroll, text mode, search) of its file
+
+use std::path::{Path, PathBuf};
+
+use egui::{RichText, Ui};
+
+use crate::files::{CacheUpdate, FileCache};
+use crate::live_watch::file_tree::SeenVersions;
+use crate::live_watch::file_view::FileView;
+use crate::live_watch::settings::TextMode;
+
+pub struct Tab {
+    key: PathBuf,
+    view: FileView,
+}
+
+impl Tab {
+    pub fn key_ref(&self) -> &PathBuf {
+        &self.key
+    }
+
+    pub fn view_ref(&self) -> &FileView {
+        &self.view
+    }
+
+    pub fn view_mut(&mut self) -> &mut FileView {
+        &mut self.view
+    }
+}
+
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: Option<usize>,
+    seen: SeenVersions,
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Tabs {
+            tabs: Vec::new(),
+            active: None,
+            seen: SeenVersions::new(),
+        }
+    }
+
+    pub fn active_ref(&self) -> Option<&Tab> {
+        self.tabs.get(self.active?)
+    }
+
+    pub fn active_mut(&mut self) -> Option<&mut Tab> {
+        self.tabs.get_mut(self.active?)
+    }
+
+    /// Switch to the tab of `key`, opening one (next to the active tab) if there is none yet
+    pub fn open(&mut self, key: PathBuf, text_mode: TextMode) {
+        if let Some(index) = self.tabs.iter().position(|tab| tab.key == key) {
+            self.active = Some(index);
+            return;
+        }
+
+        let index = self.active.map_or(self.tabs.len(), |active| active + 1);
+        let view = FileView::new(text_mode);
+        self.tabs.insert(index, Tab { key, view });
+        self.active = Some(index);
+    }
+
+    /// Close a tab, the file itself stays in the cache. Returns the key of the tab that became active
+    pub fn close(&mut self, index: usize) -> Option<PathBuf> {
+        if index >= self.tabs.len() {
+            return None;
+        }
+        self.tabs.remove(index);
+
+        let active = self.active?;
+        self.active = match active.cmp(&index) {
+            std::cmp::Ordering::Less => Some(active),
+            std::cmp::Ordering::Greater => Some(active - 1),
+            // Prefer the tab to the right, like most editors
+            std::cmp::Ordering::Equal => match self.tabs.is_empty() {
+                true => None,
+                false => Some(index.min(self.tabs.len() - 1)),
+            },
+        };
+        self.active_ref().map(|tab| tab.key.clone())
+    }
+
+    /// Follow files that were renamed (or moved with their directory) while open
+    pub fn apply(&mut self, updates: &[CacheUpdate]) {
+        for update in updates {
+            if let CacheUpdate::Renamed(from, to) = update {
+                for tab in self.tabs.iter_mut().filter(|tab| tab.key == *from) {
+                    tab.key = to.clone();
+                }
+                self.seen.rename(from, to);
+            }
+        }
+    }
+
+    fn tab_title(&self, key: &Path, file_cache: &FileCache) -> RichText {
+        let name = key.file_name().unwrap_or_default().to_string_lossy();
+        if !file_cache.contains(key) {
+            return RichText::new(format!("{} (deleted)", name)).strikethrough();
+        }
+        match self.seen.is_changed(key, file_cache) {
+            true => RichText::new(format!("{} ●", name)).strong(),
+            false => RichText::new(name),
+        }
+    }
+
+    /// Shows the tab bar, returns the key of a tab that should become the selected file
+    pub fn show(&mut self, ui: &mut Ui, file_cache: &FileCache) -> Option<PathBuf> {
+        if let Some(tab) = self.active_ref() {
+            let key = tab.key.clone();
+            self.seen.mark_seen(&key, file_cache);
+        }
+
+        let mut clicked = None;
+        let mut close = None;
+        egui::ScrollArea::horizontal()
+            .id_source("tab_bar")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, tab) in self.tabs.iter().enumerate() {
+                        let title = self.tab_title(&tab.key, file_cache);
+                        let response = ui
+                            .selectable_label(self.active == Some(index), title)
+                            .on_hover_text(tab.key.display().to_string());
+                        if response.clicked() {
+                            clicked = Some(tab.key.clone());
+                        }
+                        if ui.small_button("✖").clicked() || response.middle_clicked() {
+                            close = Some(index);
+                        }
+                        ui.separator();
+                    }
+                });
+            });
+
+        match close {
+            Some(index) => self.close(index),
+            None => clicked,
+        }
+    }
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
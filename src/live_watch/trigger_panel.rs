@@ -0,0 +1,170 @@
+//! Window for editing the on-change triggers and reading the output of their commands
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::triggers::{OutputSender, Stream, Trigger, TriggerOutput, Triggers};
+
+// Oldest lines are dropped past this
+const MAX_OUTPUT_LINES: usize = 5_000;
+
+pub struct TriggerPanel {
+    open: bool,
+    triggers: Triggers,
+    patterns: String,
+    command: String,
+    error: Option<String>,
+    receiver: UnboundedReceiver<TriggerOutput>,
+    sender: OutputSender,
+    output: VecDeque<TriggerOutput>,
+}
+
+impl TriggerPanel {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded_channel();
+        TriggerPanel {
+            open: false,
+            triggers: Arc::new(Mutex::new(Vec::new())),
+            patterns: String::new(),
+            command: String::new(),
+            error: None,
+            receiver,
+            sender,
+            output: VecDeque::new(),
+        }
+    }
+
+    pub fn triggers_clone(&self) -> Triggers {
+        //! Provide the triggers to the task running them
+        self.triggers.clone()
+    }
+
+    pub fn sender_clone(&self) -> OutputSender {
+        //! Provide a sender for the output of triggered commands
+        self.sender.clone()
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    /// Receive all output sent since the last frame
+    fn block_update_output(&mut self) {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        while let Poll::Ready(Some(output)) = self.receiver.poll_recv(&mut cx) {
+            if self.output.len() == MAX_OUTPUT_LINES {
+                self.output.pop_front();
+            }
+            self.output.push_back(output);
+        }
+    }
+
+    fn add_trigger(&mut self) {
+        match Trigger::new(&self.patterns, &self.command) {
+            Ok(trigger) => {
+                self.triggers.lock().unwrap().push(trigger);
+                self.patterns.clear();
+                self.command.clear();
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err.to_string()),
+        }
+    }
+
+    fn triggers_ui(&mut self, ui: &mut egui::Ui) {
+        let mut remove = None;
+        for (index, trigger) in self.triggers.lock().unwrap().iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("✖").clicked() {
+                    remove = Some(index);
+                }
+                ui.label(format!("When {} changes, run", trigger.patterns_ref()));
+                ui.code(trigger.command_ref());
+            });
+        }
+        if let Some(index) = remove {
+            self.triggers.lock().unwrap().remove(index);
+        }
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("When");
+            let patterns = egui::TextEdit::singleline(&mut self.patterns)
+                .hint_text("src/**/*.rs")
+                .desired_width(140.0);
+            ui.add(patterns);
+            ui.label("changes, run");
+            let command = egui::TextEdit::singleline(&mut self.command)
+                .hint_text("cargo check")
+                .desired_width(180.0);
+            let entered =
+                ui.add(command).lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || entered {
+                self.add_trigger();
+            }
+        });
+        if let Some(err) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    }
+
+    fn output_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Output");
+            if ui.button("Clear").clicked() {
+                self.output.clear();
+            }
+        });
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let error_color = ui.visuals().error_fg_color;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .stick_to_bottom(true)
+            .show_rows(ui, row_height, self.output.len(), |ui, rows| {
+                for output in self.output.range(rows) {
+                    let text = match output {
+                        TriggerOutput::Started(command) => {
+                            egui::RichText::new(format!("$ {}", command)).strong()
+                        }
+                        TriggerOutput::Line(Stream::Stdout, line) => egui::RichText::new(line),
+                        TriggerOutput::Line(Stream::Stderr, line) => {
+                            egui::RichText::new(line).color(error_color)
+                        }
+                        TriggerOutput::Finished(msg) => egui::RichText::new(msg).weak().italics(),
+                    };
+                    ui.label(text.monospace());
+                }
+            });
+    }
+
+    /// Shows the window (while open)
+    pub fn show(&mut self, ctx: &egui::Context) {
+        // Keep receiving while closed, so the output is complete whenever it is opened
+        self.block_update_output();
+        if !self.open {
+            return;
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Triggers")
+            .resizable(true)
+            .open(&mut open)
+            .show(ctx, |ui| {
+                self.triggers_ui(ui);
+                ui.separator();
+                self.output_ui(ui);
+            });
+        self.open = open;
+    }
+}
+
+impl Default for TriggerPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
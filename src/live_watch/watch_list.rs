@@ -0,0 +1,95 @@
+//! The Watch List: the file store of the watcher, with the combo box picking which file to show
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::changes::Change;
+use crate::eframe_tools::ModalMachine;
+use crate::files::{FileCache, FileStore};
+use crate::list_filters::ListFilters;
+use crate::watching::watcher_keep::WatcherUpdate;
+
+pub struct WatchList {
+    mm: ModalMachine,
+    store: FileStore,
+}
+
+// Private
+impl WatchList {
+    fn get_modal_machine_mut(&mut self) -> &mut ModalMachine {
+        &mut self.mm
+    }
+
+    fn get_modal_machine_ref(&self) -> &ModalMachine {
+        &self.mm
+    }
+}
+
+impl WatchList {
+    pub fn modal_machine_mut(&mut self) -> &mut ModalMachine {
+        self.get_modal_machine_mut()
+    }
+    pub fn modal_machine_ref(&self) -> &ModalMachine {
+        self.get_modal_machine_ref()
+    }
+
+    pub fn file_cache_ref(&self) -> &FileCache {
+        self.store.file_cache_ref()
+    }
+
+    pub fn file_cache_mut(&mut self) -> &mut FileCache {
+        self.store.file_cache_mut()
+    }
+
+    pub fn filters_ref(&self) -> &ListFilters {
+        self.store.filters_ref()
+    }
+}
+
+// Public
+impl WatchList {
+    pub fn new(store: FileStore, name: impl Into<String>) -> WatchList {
+        let options: BTreeMap<PathBuf, ()> = store
+            .file_cache_ref()
+            .keys()
+            .map(|key| (key.to_owned(), ()))
+            .collect();
+        let first = options.keys().next().cloned().unwrap_or_default();
+        let mm = ModalMachine::new(first, options, name.into());
+
+        WatchList { mm, store }
+    }
+
+    /// Swap in new filters, listing the tree again and loading whatever became visible
+    pub fn set_filters(&mut self, filters: ListFilters) {
+        self.store.set_filters(filters);
+        self.refresh_options();
+    }
+
+    fn refresh_options(&mut self) {
+        let options = self
+            .file_cache_ref()
+            .keys()
+            .map(|pb| (pb.to_owned(), ()))
+            .collect();
+        self.modal_machine_mut().replace_options(options);
+    }
+
+    /// List the files below other roots, returns the broken lines of their ignore files
+    pub fn switch_roots(&mut self, roots: &[PathBuf]) -> Vec<String> {
+        let errors = self.store.switch_roots(roots);
+        self.refresh_options();
+        errors
+    }
+
+    /// Apply an update from the watcher, returns what it changed
+    pub fn apply(&mut self, watcher_update: WatcherUpdate) -> Vec<Change> {
+        let listing_version = self.file_cache_ref().listing_version();
+        let changes = Change::apply(&mut self.store, watcher_update);
+        if self.file_cache_ref().listing_version() != listing_version {
+            self.refresh_options();
+        }
+
+        changes
+    }
+}
@@ -1,12 +1,53 @@
+use live_watch::cmd_args;
+#[cfg(feature = "gui")]
+use live_watch::control::{self, Request};
+use live_watch::headless;
+#[cfg(feature = "gui")]
 use live_watch::live_watch::live_watch_app::LiveWatch;
 
 // #[tokio::main]
 fn main() {
+    // Before any window opens, so --help and bad arguments stay on the terminal
+    let args = cmd_args::parse();
+
+    // Built without the window, the terminal is all there is
+    if args.headless || args.json || args.serve.is_some() || !cfg!(feature = "gui") {
+        if let Err(err) = headless::run(args) {
+            eprintln!("live_watch: {}", err);
+            std::process::exit(1);
+        }
+    } else {
+        #[cfg(feature = "gui")]
+        run_window(args);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_window(args: cmd_args::Args) {
+    // A window that is already open shows the paths instead, what is piped in can not be handed over
+    if !args.new_window && !args.stdin {
+        let socket = args
+            .socket
+            .clone()
+            .unwrap_or_else(control::default_socket_path);
+        let open: Vec<Request> = args
+            .paths
+            .iter()
+            .map(|path| Request::Open { path: path.clone() })
+            .collect();
+        if let Ok(replies) = control::send(&socket, &open) {
+            for err in replies.into_iter().filter_map(|reply| reply.error) {
+                eprintln!("live_watch: {}", err);
+            }
+            return;
+        }
+    }
+
     let option = eframe::NativeOptions::default();
     eframe::run_native(
         "live_watch",
         option,
-        Box::new(|_cc| Box::new(LiveWatch::default())),
+        Box::new(|_cc| Box::new(LiveWatch::new(args))),
     )
     .unwrap();
 }
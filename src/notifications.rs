@@ -0,0 +1,191 @@
+//! Desktop notifications for changes to files the user asked to hear about
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::files::inside_root;
+use crate::messages::ErrorSender;
+use crate::messages::Loglet;
+use crate::string_tools::changed_lines;
+use crate::MagicError;
+
+/// What gets shown to the user
+#[derive(PartialEq, Clone, Debug)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Where notifications end up, the desktop in the app and a mock in tests
+pub trait Notifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), MagicError>;
+}
+
+/// Freedesktop notifications (through D-Bus)
+pub struct DesktopNotifier {
+    err_tx: ErrorSender,
+}
+
+impl DesktopNotifier {
+    pub fn new(err_tx: ErrorSender) -> Self {
+        DesktopNotifier { err_tx }
+    }
+}
+
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), MagicError> {
+        // Talking to the notification daemon blocks, so keep it off the UI thread
+        let notification = notification.clone();
+        let err_tx = self.err_tx.clone();
+        std::thread::spawn(move || {
+            let shown = notify_rust::Notification::new()
+                .appname("live_watch")
+                .summary(&notification.summary)
+                .body(&notification.body)
+                .show();
+            if let Err(err) = shown {
+                let _ = err_tx.blocking_send(Loglet::err_s(err.to_string()));
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// A short description of how `new` differs from `old`, line by line, see [`changed_lines`]
+pub fn diff_summary(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let (prefix, removed, added) = changed_lines(&old, &new);
+
+    if added == 0 && removed == 0 {
+        return String::from("No line changes");
+    }
+    let mut summary = format!("+{} -{} lines at line {}", added, removed, prefix + 1);
+    if let Some(first) = new.get(prefix).filter(|_| added > 0) {
+        let preview: String = first.trim().chars().take(80).collect();
+        summary.push_str(&format!(": {}", preview));
+    }
+    summary
+}
+
+/// Decides which changes are worth a notification and sends them
+pub struct ChangeNotifier {
+    notifier: Box<dyn Notifier>,
+    files: BTreeSet<PathBuf>,
+    patterns: String,
+    globs: Option<GlobSet>,
+    min_interval: Duration,
+    last_sent: HashMap<PathBuf, Instant>,
+    // The text each watched file had at its last notification, to diff against
+    snapshots: HashMap<PathBuf, String>,
+}
+
+impl ChangeNotifier {
+    pub fn new(notifier: Box<dyn Notifier>, min_interval: Duration) -> Self {
+        ChangeNotifier {
+            notifier,
+            files: BTreeSet::new(),
+            patterns: String::new(),
+            globs: None,
+            min_interval,
+            last_sent: HashMap::new(),
+            snapshots: HashMap::new(),
+        }
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter()
+    }
+
+    pub fn patterns_ref(&self) -> &str {
+        &self.patterns
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    pub fn set_min_interval(&mut self, min_interval: Duration) {
+        self.min_interval = min_interval;
+    }
+
+    /// Notify about `key`, `text` is what it holds right now (the starting point of the first diff)
+    pub fn watch_file(&mut self, key: PathBuf, text: Option<&str>) {
+        if let Some(text) = text {
+            self.snapshots.insert(key.clone(), text.to_owned());
+        }
+        self.files.insert(key);
+    }
+
+    pub fn unwatch_file(&mut self, key: &Path) {
+        self.files.remove(key);
+        self.snapshots.remove(key);
+    }
+
+    /// Notify about every file matching one of the (comma or space separated) globs
+    pub fn set_patterns(&mut self, patterns: &str) -> Result<(), MagicError> {
+        let mut builder = GlobSetBuilder::new();
+        let mut any = false;
+        for pattern in patterns.split([',', ' ']).filter(|p| !p.is_empty()) {
+            builder.add(Glob::new(pattern)?);
+            any = true;
+        }
+        self.globs = match any {
+            true => Some(builder.build()?),
+            false => None,
+        };
+        self.patterns = patterns.to_owned();
+
+        Ok(())
+    }
+
+    /// Remember the current text of `key` (if it is watched), so its first notification has something to diff against
+    pub fn seed(&mut self, key: &Path, text: &str) {
+        if self.is_watched(key) && !self.snapshots.contains_key(key) {
+            self.snapshots.insert(key.to_path_buf(), text.to_owned());
+        }
+    }
+
+    pub fn is_watched(&self, key: &Path) -> bool {
+        // Globs are matched inside the root, like trigger and follow globs
+        let inside = inside_root(key);
+        self.files.contains(key)
+            || self
+                .globs
+                .as_ref()
+                .is_some_and(|globs| globs.is_match(inside))
+    }
+
+    /// Call with the new text of every file that changed, returns what was sent (if anything)
+    pub fn changed(&mut self, key: &Path, text: &str) -> Result<Option<Notification>, MagicError> {
+        if !self.is_watched(key) {
+            return Ok(None);
+        }
+        let rate_limited = self
+            .last_sent
+            .get(key)
+            .is_some_and(|sent| sent.elapsed() < self.min_interval);
+        if rate_limited {
+            return Ok(None);
+        }
+        // Changes held back by the rate limit are part of the next diff
+        let old = self.snapshots.insert(key.to_path_buf(), text.to_owned());
+
+        let name = key.file_name().unwrap_or(key.as_os_str());
+        let notification = Notification {
+            summary: format!("{} changed", name.to_string_lossy()),
+            body: match old {
+                Some(old) => diff_summary(&old, text),
+                None => key.display().to_string(),
+            },
+        };
+        self.notifier.notify(&notification)?;
+        self.last_sent.insert(key.to_path_buf(), Instant::now());
+
+        Ok(Some(notification))
+    }
+}
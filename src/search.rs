@@ -0,0 +1,110 @@
+//! Text search over file contents
+
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::MagicError;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SearchMode {
+    Plain,
+    CaseInsensitive,
+    Regex,
+}
+
+/// A line of a file that holds at least one match
+pub struct LineHit {
+    pub line: usize,
+    pub text: String,
+}
+
+/// A compiled search query
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    pub fn new(query: &str, mode: &SearchMode) -> Result<Matcher, MagicError> {
+        let pattern = match mode {
+            SearchMode::Regex => query.to_owned(),
+            SearchMode::Plain | SearchMode::CaseInsensitive => regex::escape(query),
+        };
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(*mode == SearchMode::CaseInsensitive)
+            .multi_line(true)
+            .build()?;
+
+        Ok(Matcher { regex })
+    }
+
+    /// Byte ranges of every (non empty) match in `text`, in order
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .map(|found| found.range())
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+
+    /// Every line of `text` holding a match (1 based line numbers), at most `limit` of them
+    pub fn line_hits(&self, text: &str, limit: usize) -> Vec<LineHit> {
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| self.regex.find_iter(line).any(|found| !found.is_empty()))
+            .take(limit)
+            .map(|(index, line)| LineHit {
+                line: index + 1,
+                text: line.to_owned(),
+            })
+            .collect()
+    }
+}
+
+fn is_boundary(previous: Option<char>) -> bool {
+    previous.is_none_or(|previous| matches!(previous, '/' | '\\' | '_' | '-' | '.' | ' '))
+}
+
+/// Scores how well `pattern` fuzzy matches `candidate` (its characters appear in order), None if it does not
+///
+/// Consecutive characters, characters at the start of a word and matches inside the file name score higher.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let file_name_start = candidate.rfind(['/', '\\']).map_or(0, |index| index + 1);
+    let mut pattern = pattern
+        .chars()
+        .filter(|chr| !chr.is_whitespace())
+        .peekable();
+    let mut score = 0;
+    let mut previous = None;
+    let mut last_match: Option<usize> = None;
+
+    for (position, (index, chr)) in candidate.char_indices().enumerate() {
+        let Some(wanted) = pattern.peek() else {
+            break;
+        };
+        if chr.to_lowercase().eq(wanted.to_lowercase()) {
+            score += 1;
+            if last_match.is_some_and(|last| last + 1 == position) {
+                score += 5;
+            }
+            if is_boundary(previous) {
+                score += 8;
+            }
+            if index >= file_name_start {
+                score += 2;
+            }
+            if let Some(last) = last_match {
+                score -= (position - last - 1).min(5) as i64;
+            }
+            last_match = Some(position);
+            pattern.next();
+        }
+        previous = Some(chr);
+    }
+
+    match pattern.peek() {
+        Some(_) => None,
+        // Prefer shorter paths when everything else is equal
+        None => Some(score * 10 - candidate.len() as i64 / 10),
+    }
+}
@@ -0,0 +1,140 @@
+//! Sharing the watched files over HTTP, so teammates can follow along in a browser
+//!
+//! - `GET /` a page listing the files, showing the one picked and following it as it changes
+//! - `GET /files` every listed file as JSON, `[{"path":"src/main.rs","size":812}, …]` (no size when not loaded)
+//! - `GET /files/<path>` the text of one file
+//! - `GET /events` changes as Server-Sent Events, each `data` the same JSON object `--json` prints
+//!
+//! Nothing is written through it, but everything watched is readable by whoever reaches the address.
+
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{Path as UrlPath, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use futures::Stream;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::files::{File, FileStore};
+use crate::json_events::JsonEvent;
+
+/// How many events a browser may fall behind before it has to list the files again
+const EVENT_BACKLOG: usize = 256;
+
+const PAGE: &str = include_str!("server/index.html");
+
+pub struct Server {
+    store: Arc<RwLock<FileStore>>,
+    event_tx: broadcast::Sender<JsonEvent>,
+}
+
+#[derive(Serialize)]
+struct ListedFile {
+    path: String,
+    size: Option<u64>,
+}
+
+impl Server {
+    /// Serve `store`, whoever applies updates to it also [`Server::publish`]es what they changed
+    pub fn new(store: Arc<RwLock<FileStore>>) -> Server {
+        let (event_tx, _) = broadcast::channel(EVENT_BACKLOG);
+        Server { store, event_tx }
+    }
+
+    /// Push an event to every open `/events` stream
+    pub fn publish(&self, event: JsonEvent) {
+        // Nobody listening is fine
+        let _ = self.event_tx.send(event);
+    }
+
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new()
+            .route("/", get(|| async { Html(PAGE) }))
+            .route("/files", get(list_files))
+            .route("/files/*key", get(file_text))
+            .route("/events", get(events))
+            .with_state(self)
+    }
+}
+
+async fn list_files(State(server): State<Arc<Server>>) -> Json<Vec<ListedFile>> {
+    let store = server.store.read().unwrap();
+    let file_cache = store.file_cache_ref();
+    let files = file_cache
+        .keys()
+        .map(|key| ListedFile {
+            path: key.to_string_lossy().into_owned(),
+            size: file_cache
+                .file_ref(key)
+                .map(|file| file.file_form_ref().text_ref().len() as u64),
+        })
+        .collect();
+
+    Json(files)
+}
+
+async fn file_text(
+    State(server): State<Arc<Server>>,
+    UrlPath(key): UrlPath<String>,
+) -> Result<String, (StatusCode, String)> {
+    let key = PathBuf::from(key);
+    let path = {
+        let store = server.store.read().unwrap();
+        let file_cache = store.file_cache_ref();
+        if !file_cache.contains(&key) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                format!("{} is not watched", key.display()),
+            ));
+        }
+        if let Some(file) = file_cache.file_ref(&key) {
+            return Ok(file.file_form_ref().text_ref().to_owned());
+        }
+        file_cache.absolute_path(&key)
+    };
+
+    // Files left out for their size are read for the request, without keeping them in the cache
+    let read = tokio::task::spawn_blocking(move || {
+        File::load_file(&path)
+            .map(|file| file.file_form_ref().text_ref().to_owned())
+            .map_err(|err| err.to_string())
+    })
+    .await;
+    match read {
+        Ok(Ok(text)) => Ok(text),
+        Ok(Err(err)) => Err((StatusCode::INTERNAL_SERVER_ERROR, err)),
+        Err(err) => Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string())),
+    }
+}
+
+async fn events(
+    State(server): State<Arc<Server>>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let event_rx = server.event_tx.subscribe();
+    let stream = futures::stream::unfold(event_rx, |mut event_rx| async move {
+        let event = match event_rx.recv().await {
+            Ok(json_event) => Event::default().data(json_event.to_line()),
+            // Too far behind to catch up, the page lists the files again
+            Err(broadcast::error::RecvError::Lagged(_)) => {
+                Event::default().event("resync").data("")
+            }
+            Err(broadcast::error::RecvError::Closed) => return None,
+        };
+        Some((Ok(event), event_rx))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Answer requests on `listener` until the runtime shuts down
+pub async fn serve(server: Arc<Server>, listener: std::net::TcpListener) -> std::io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::TcpListener::from_std(listener)?;
+    axum::serve(listener, server.router()).await
+}
@@ -0,0 +1,242 @@
+//! Commands the user wants run whenever matching files change (think `entr` or `watchexec`)
+
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, RwLock};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use crate::messages::ErrorSender;
+use crate::messages::Loglet;
+use crate::time_of_day;
+use crate::MagicError;
+
+/// "When one of `patterns` changes, run `command`"
+pub struct Trigger {
+    patterns: String,
+    globs: GlobSet,
+    command: String,
+}
+
+impl Trigger {
+    /// `patterns` are comma or space separated globs, relative to the watched directory (`src/**/*.rs`)
+    pub fn new(patterns: &str, command: &str) -> Result<Self, MagicError> {
+        let mut builder = GlobSetBuilder::new();
+        let mut any = false;
+        for pattern in patterns.split([',', ' ']).filter(|p| !p.is_empty()) {
+            builder.add(Glob::new(pattern)?);
+            any = true;
+        }
+        if !any {
+            return Err("A trigger needs at least one glob".into());
+        }
+        if command.trim().is_empty() {
+            return Err("A trigger needs a command to run".into());
+        }
+
+        Ok(Trigger {
+            patterns: patterns.to_owned(),
+            globs: builder.build()?,
+            command: command.to_owned(),
+        })
+    }
+
+    pub fn patterns_ref(&self) -> &str {
+        &self.patterns
+    }
+
+    pub fn command_ref(&self) -> &str {
+        &self.command
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        self.globs.is_match(path)
+    }
+}
+
+/// Shared between the window editing the triggers and the task running them
+pub type Triggers = Arc<Mutex<Vec<Trigger>>>;
+
+/// The watched roots, switched by the window while the task running triggers reads them
+pub type Roots = Arc<RwLock<Vec<PathBuf>>>;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// What a run has to say, in the order it happened
+#[derive(PartialEq, Clone, Debug)]
+pub enum TriggerOutput {
+    Started(String),
+    Line(Stream, String),
+    Finished(String),
+}
+
+pub type OutputSender = UnboundedSender<TriggerOutput>;
+
+/// Describe how a run of `command` ended, `None` when it was cancelled
+fn exit_message(command: &str, status: Option<std::io::Result<ExitStatus>>) -> String {
+    match status {
+        Some(Ok(status)) => format!("`{}` finished with {}", command, status),
+        Some(Err(err)) => format!("`{}` could not be waited on: {}", command, err),
+        None => format!("`{}` was cancelled by a newer change", command),
+    }
+}
+
+/// Send every line of `reader` as output, until the process closes it
+async fn forward_lines(reader: impl AsyncRead + Unpin, stream: Stream, output_tx: OutputSender) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let _ = output_tx.send(TriggerOutput::Line(stream, line));
+    }
+}
+
+/// Stop the shell and everything it started (`cargo check` runs in its own processes)
+fn kill_process_group(pid: u32) {
+    // SAFETY: kill only sends a signal, the negative pid addresses the group the child leads
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), libc::SIGTERM);
+    }
+}
+
+async fn run_command(
+    command: String,
+    root: PathBuf,
+    output_tx: OutputSender,
+    err_tx: ErrorSender,
+    cancel_rx: oneshot::Receiver<()>,
+) {
+    let _ = output_tx.send(TriggerOutput::Started(command.clone()));
+    let mut shell = std::process::Command::new("sh");
+    // Its own process group, so cancelling reaches everything the command started
+    shell
+        .arg("-c")
+        .arg(&command)
+        .current_dir(&root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    let spawned = Command::from(shell).kill_on_drop(true).spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(err) => {
+            let msg = format!("`{}` could not be started: {}", command, err);
+            let _ = output_tx.send(TriggerOutput::Finished(msg.clone()));
+            let _ = err_tx.send(Loglet::err_s(msg)).await;
+            return;
+        }
+    };
+
+    let mut forwarders = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        let output_tx = output_tx.clone();
+        forwarders.push(tokio::spawn(forward_lines(
+            stdout,
+            Stream::Stdout,
+            output_tx,
+        )));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let output_tx = output_tx.clone();
+        forwarders.push(tokio::spawn(forward_lines(
+            stderr,
+            Stream::Stderr,
+            output_tx,
+        )));
+    }
+
+    let status = tokio::select! {
+        status = child.wait() => Some(status),
+        _ = cancel_rx => {
+            if let Some(pid) = child.id() {
+                kill_process_group(pid);
+            }
+            let _ = child.kill().await;
+            None
+        }
+    };
+    // Whatever was printed before the end comes before the exit message
+    for forwarder in forwarders {
+        let _ = forwarder.await;
+    }
+
+    let msg = exit_message(&command, status);
+    let _ = output_tx.send(TriggerOutput::Finished(msg.clone()));
+    let _ = err_tx
+        .send(Loglet::new("Trigger", &msg, &time_of_day()))
+        .await;
+}
+
+// A run that is still going, with the means to stop it
+struct Run {
+    cancel_tx: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+/// Where commands for a change to `path` run, and the path (inside that directory) trigger globs are matched against
+fn relative_to<'a>(roots: &[PathBuf], path: &'a Path) -> Option<(PathBuf, &'a Path)> {
+    roots.iter().find_map(|root| match path.strip_prefix(root) {
+        Ok(inside) if !inside.as_os_str().is_empty() => Some((root.clone(), inside)),
+        // A file watched on its own, matched by its name and run next to it
+        Ok(_) => Some((root.parent()?.to_path_buf(), Path::new(path.file_name()?))),
+        Err(_) => None,
+    })
+}
+
+/// Run the matching triggers for every (debounced) batch of changed paths
+///
+/// Runs still going when a new batch matches are cancelled first, so only the newest state of the tree is checked.
+/// Commands run in the root the change happened in, their output goes to `output_tx` and their exit status to the
+/// message log.
+pub async fn run_triggers(
+    triggers: Triggers,
+    roots: Roots,
+    mut batch_rx: UnboundedReceiver<Vec<PathBuf>>,
+    output_tx: OutputSender,
+    err_tx: ErrorSender,
+) {
+    let mut running: Vec<Run> = Vec::new();
+
+    while let Some(batch) = batch_rx.recv().await {
+        let changed: Vec<(PathBuf, &Path)> = {
+            let roots = roots.read().unwrap();
+            batch
+                .iter()
+                .filter_map(|path| relative_to(&roots, path))
+                .collect()
+        };
+        let mut commands: Vec<(String, PathBuf)> = Vec::new();
+        for trigger in triggers.lock().unwrap().iter() {
+            for (root, _) in changed.iter().filter(|(_, path)| trigger.matches(path)) {
+                let command = (trigger.command_ref().to_owned(), root.clone());
+                if !commands.contains(&command) {
+                    commands.push(command);
+                }
+            }
+        }
+        if commands.is_empty() {
+            continue;
+        }
+
+        for run in running.drain(..) {
+            // Already finished if the receiver is gone
+            let _ = run.cancel_tx.send(());
+            let _ = run.handle.await;
+        }
+        for (command, root) in commands {
+            let (cancel_tx, cancel_rx) = oneshot::channel();
+            let run = run_command(command, root, output_tx.clone(), err_tx.clone(), cancel_rx);
+            let handle = tokio::spawn(run);
+            running.push(Run { cancel_tx, handle });
+        }
+    }
+}
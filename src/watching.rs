@@ -0,0 +1,31 @@
+//! Watching without a window: the part of live_watch other tools can embed
+//!
+//! A [`watcher::Watcher`] watches roots and streams [`watcher::WatchEvent`]s, a [`crate::files::FileStore`] keeps
+//! the text of every file below them current. The window and `--headless` are both built on the two.
+//!
+//! ```no_run
+//! use std::sync::{Arc, Mutex};
+//! use live_watch::list_filters::ListFilters;
+//! use live_watch::watching::watcher::{WatchEvent, WatchOptions, Watcher};
+//!
+//! let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
+//! let roots = vec![std::env::current_dir().unwrap()];
+//! let mut watcher = Watcher::new(&roots, &WatchOptions::new(), rt.clone()).unwrap();
+//! let mut store = watcher.file_store(ListFilters::new());
+//!
+//! let rt_handle = rt.lock().unwrap().handle().clone();
+//! while let Some(event) = rt_handle.block_on(watcher.next()) {
+//!     if let WatchEvent::Update(update) = event {
+//!         for change in store.apply(update) {
+//!             println!("{:?}", change);
+//!         }
+//!     }
+//! }
+//! ```
+
+pub mod activity;
+pub mod backend;
+pub mod session;
+pub mod stdin;
+pub mod watcher;
+pub mod watcher_keep;
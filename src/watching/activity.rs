@@ -0,0 +1,92 @@
+//! Every filesystem event the watcher sees, as it came from notify (ignored paths left out)
+
+use std::path::PathBuf;
+
+use notify::event::{EventKind, ModifyKind};
+use tokio::sync::mpsc::UnboundedSender;
+
+pub type ActivitySender = UnboundedSender<Activity>;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ActivityKind {
+    Create,
+    Modify,
+    Rename,
+    Delete,
+    Access,
+    Other,
+}
+
+impl ActivityKind {
+    pub const ALL: [ActivityKind; 6] = [
+        ActivityKind::Create,
+        ActivityKind::Modify,
+        ActivityKind::Rename,
+        ActivityKind::Delete,
+        ActivityKind::Access,
+        ActivityKind::Other,
+    ];
+
+    fn from_event_kind(kind: &EventKind) -> Self {
+        match kind {
+            EventKind::Create(_) => ActivityKind::Create,
+            EventKind::Modify(ModifyKind::Name(_)) => ActivityKind::Rename,
+            EventKind::Modify(_) => ActivityKind::Modify,
+            EventKind::Remove(_) => ActivityKind::Delete,
+            EventKind::Access(_) => ActivityKind::Access,
+            EventKind::Any | EventKind::Other => ActivityKind::Other,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ActivityKind::Create => "Create",
+            ActivityKind::Modify => "Modify",
+            ActivityKind::Rename => "Rename",
+            ActivityKind::Delete => "Delete",
+            ActivityKind::Access => "Access",
+            ActivityKind::Other => "Other",
+        }
+    }
+}
+
+/// One filesystem event, as it came from notify
+pub struct Activity {
+    time: String,
+    kind: ActivityKind,
+    detail: String,
+    paths: Vec<PathBuf>,
+}
+
+impl Activity {
+    pub fn from_event(event: &notify::Event) -> Self {
+        Activity {
+            time: chrono::Local::now().format("%H:%M:%S%.3f").to_string(),
+            kind: ActivityKind::from_event_kind(&event.kind),
+            detail: format!("{:?}", event.kind),
+            paths: event.paths.clone(),
+        }
+    }
+
+    /// Local time of day, to the millisecond
+    pub fn time_ref(&self) -> &str {
+        &self.time
+    }
+
+    pub fn kind(&self) -> ActivityKind {
+        self.kind
+    }
+
+    /// The event as notify describes it, more detailed than its kind
+    pub fn detail_ref(&self) -> &str {
+        &self.detail
+    }
+
+    pub fn paths_ref(&self) -> &Vec<PathBuf> {
+        &self.paths
+    }
+}
@@ -0,0 +1,305 @@
+//! What notices changes on disk: kernel notifications, or scanning the tree over and over
+//!
+//! inotify only hears about changes made through the local kernel, so on NFS, SSHFS, SMB and the FUSE mounts of
+//! container runtimes it stays silent. Polling works everywhere, at the price of a scan every interval. Modification
+//! times are only compared to the second, so the poller hashes file contents as well to see every edit.
+//!
+//! inotify needs a watch per directory, and there are only `fs.inotify.max_user_watches` of them. The directories it
+//! runs out of watches for are polled instead, see [`Backends::watch`].
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use notify::{Config, EventHandler, RecursiveMode, Watcher};
+use walkdir::WalkDir;
+
+use crate::cmd_args::Backend;
+use crate::watching::session::{ReplayWatcher, Session};
+use crate::watching::watcher::WatchOptions;
+use crate::{MagicError, WatcherX};
+
+/// How often the polling backend scans the tree, unless told otherwise
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Filesystems whose changes inotify may never hear about, by their `statfs` magic number
+const NETWORK_FILESYSTEMS: [(u32, &str); 9] = [
+    (0x6969, "nfs"),
+    (0x517b, "smb"),
+    (0xff53_4d42, "cifs"),
+    (0xfe53_4d42, "smb2"),
+    (0x6573_5546, "fuse"),
+    (0x0102_1997, "9p"),
+    (0x7375_7245, "coda"),
+    (0x5346_414f, "afs"),
+    (0x00c3_6400, "ceph"),
+];
+
+/// Which watcher does the watching
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum WatchBackend {
+    /// Kernel notifications, immediate but local filesystems only
+    Inotify,
+    /// Scanning the tree every [`WatchOptions::poll_interval`](crate::watching::watcher::WatchOptions::poll_interval)
+    Poll,
+    /// inotify, unless a root is on a network filesystem
+    Auto,
+    /// A recorded session instead of the disk, see [`WatchOptions::replay`]
+    Replay,
+}
+
+impl From<Backend> for WatchBackend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Inotify => WatchBackend::Inotify,
+            Backend::Poll => WatchBackend::Poll,
+            Backend::Auto => WatchBackend::Auto,
+        }
+    }
+}
+
+impl WatchBackend {
+    /// The backend watching `roots`: [`WatchBackend::Auto`] is decided here, the others stay what they are
+    pub fn resolve(self, roots: &[PathBuf]) -> WatchBackend {
+        match self {
+            WatchBackend::Auto if network_root(roots).is_some() => WatchBackend::Poll,
+            WatchBackend::Auto => WatchBackend::Inotify,
+            backend => backend,
+        }
+    }
+}
+
+/// The name of the network filesystem `path` is on, None when it is local (or can not be told)
+pub fn network_filesystem(path: &Path) -> Option<&'static str> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: path is a valid C string, stat is written to and nothing else
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // The width of f_type differs between architectures, the magic numbers all fit in 32 bits
+    let magic = stat.f_type as u32;
+    NETWORK_FILESYSTEMS
+        .iter()
+        .find(|(network_magic, _)| *network_magic == magic)
+        .map(|(_, name)| *name)
+}
+
+/// The first of `roots` on a network filesystem, with the name of that filesystem
+pub fn network_root(roots: &[PathBuf]) -> Option<(&PathBuf, &'static str)> {
+    roots
+        .iter()
+        .find_map(|root| Some((root, network_filesystem(root)?)))
+}
+
+/// What to tell the user about watching `roots` with the `requested` backend, if anything
+pub fn notice(
+    requested: WatchBackend,
+    roots: &[PathBuf],
+    poll_interval: Duration,
+) -> Option<String> {
+    let (root, filesystem) = network_root(roots)?;
+    match requested {
+        WatchBackend::Auto => Some(format!(
+            "'{}' is on {}, polling every {}ms instead of using inotify",
+            root.display(),
+            filesystem,
+            poll_interval.as_millis()
+        )),
+        WatchBackend::Inotify => Some(format!(
+            "'{}' is on {}, inotify may not hear about changes made on other machines",
+            root.display(),
+            filesystem
+        )),
+        WatchBackend::Poll | WatchBackend::Replay => None,
+    }
+}
+
+/// A watcher of the (resolved) `backend`, sending what it notices to `handler`
+pub fn create_watcher(
+    backend: WatchBackend,
+    options: &WatchOptions,
+    handler: impl EventHandler,
+) -> Result<WatcherX, MagicError> {
+    let watcher: WatcherX = match backend {
+        WatchBackend::Poll => Box::new(notify::PollWatcher::new(
+            handler,
+            Config::default()
+                .with_poll_interval(options.poll_interval)
+                .with_compare_contents(true),
+        )?),
+        WatchBackend::Replay => {
+            let path = options.replay.as_ref().ok_or("no session to replay")?;
+            Box::new(ReplayWatcher::new(Session::load(path)?, handler))
+        }
+        WatchBackend::Inotify | WatchBackend::Auto => {
+            Box::new(notify::INotifyWatcher::new(handler, Config::default())?)
+        }
+    };
+    Ok(watcher)
+}
+
+/// How many directories a watch covers, as of when its roots were watched
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub struct WatchedDirs {
+    /// Watched by the backend the watch started with
+    pub watched: usize,
+    /// Polled instead, inotify ran out of watches for them
+    pub polled: usize,
+}
+
+/// Makes the poller standing in for inotify, called once inotify runs out of watches
+pub type MakeFallback = Box<dyn FnOnce() -> Result<WatcherX, MagicError> + Send>;
+
+/// The watchers of a running watch: the one of its backend, and the poller standing in when inotify runs out
+pub struct Backends {
+    watcher: WatcherX,
+    make_fallback: Option<MakeFallback>,
+    fallback: Option<WatcherX>,
+    watched: Vec<PathBuf>,
+    polled: Vec<PathBuf>,
+    watched_dirs: Arc<Mutex<WatchedDirs>>,
+}
+
+impl Backends {
+    /// `make_fallback` makes a poller when `watcher` is inotify, None otherwise
+    pub fn new(
+        watcher: WatcherX,
+        make_fallback: Option<MakeFallback>,
+        watched_dirs: Arc<Mutex<WatchedDirs>>,
+    ) -> Backends {
+        Backends {
+            watcher,
+            make_fallback,
+            fallback: None,
+            watched: Vec::new(),
+            polled: Vec::new(),
+            watched_dirs,
+        }
+    }
+
+    /// Watch `root` recursively, returns what the user should know about it, if anything
+    ///
+    /// inotify watches directory after directory, in the order [`WalkDir`] lists them. When it runs out of watches
+    /// partway through it keeps the ones it got, and the directories it did not get to are polled instead: the one it
+    /// failed on and every directory listed after it, as few subtrees as cover them. Directories made later on are
+    /// left unwatched if the watches run out then, notify does not say.
+    pub fn watch(&mut self, root: &Path) -> Option<String> {
+        let err = match self.watcher.watch(root, RecursiveMode::Recursive) {
+            Ok(()) => {
+                self.watched.push(root.to_path_buf());
+                self.watched_dirs.lock().unwrap().watched += list_dirs(root).len();
+                return None;
+            }
+            Err(err) => err,
+        };
+        if !matches!(err.kind, notify::ErrorKind::MaxFilesWatch) {
+            return Some(format!("Can not watch '{}': {}", root.display(), err));
+        }
+
+        let dirs = list_dirs(root);
+        // The tree changed since inotify listed it when the directory it failed on is not found, poll all of it
+        let failed = err
+            .paths
+            .first()
+            .and_then(|failed| dirs.iter().position(|dir| dir == failed));
+        let watched = match failed {
+            Some(failed) => {
+                self.watched.push(root.to_path_buf());
+                failed
+            }
+            None => {
+                let _ = self.watcher.unwatch(root);
+                0
+            }
+        };
+        let subtrees = subtrees(&dirs[watched..]);
+        let polled = self.fallback().is_some_and(|fallback| {
+            subtrees
+                .iter()
+                .all(|subtree| fallback.watch(subtree, RecursiveMode::Recursive).is_ok())
+        });
+        // Subtrees polled before one failed stay polled, they are given back with the rest
+        self.polled.extend(subtrees);
+
+        let mut watched_dirs = self.watched_dirs.lock().unwrap();
+        watched_dirs.watched += watched;
+        if polled {
+            watched_dirs.polled += dirs.len() - watched;
+        }
+        Some(watch_limit_message(
+            root,
+            dirs.len(),
+            dirs.len() - watched,
+            polled,
+        ))
+    }
+
+    /// The poller, made the first time it is needed (it runs a thread of its own)
+    fn fallback(&mut self) -> Option<&mut WatcherX> {
+        if let Some(make_fallback) = self.make_fallback.take() {
+            self.fallback = make_fallback().ok();
+        }
+        self.fallback.as_mut()
+    }
+
+    /// Stop watching (and polling) every root
+    pub fn unwatch_all(&mut self) {
+        for root in self.watched.drain(..) {
+            let _ = self.watcher.unwatch(&root);
+        }
+        if let Some(fallback) = &mut self.fallback {
+            for root in self.polled.drain(..) {
+                let _ = fallback.unwatch(&root);
+            }
+        }
+        *self.watched_dirs.lock().unwrap() = WatchedDirs::default();
+    }
+}
+
+/// Directories below `root` (itself included) in the order inotify watches them, it needs a watch for every one
+fn list_dirs(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+/// The fewest directories whose subtrees hold all of `dirs`, which are listed parents first
+fn subtrees(dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut subtrees: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if !subtrees.iter().any(|subtree| dir.starts_with(subtree)) {
+            subtrees.push(dir.clone());
+        }
+    }
+    subtrees
+}
+
+/// Why (part of) `root` is not watched by inotify, and how to get it back
+fn watch_limit_message(root: &Path, dirs: usize, unwatched: usize, polled: bool) -> String {
+    let limit = std::fs::read_to_string("/proc/sys/fs/inotify/max_user_watches")
+        .ok()
+        .and_then(|limit| limit.trim().parse::<usize>().ok());
+    let limit_text = limit.map_or(String::from("unknown"), |limit| limit.to_string());
+    // Watches are shared with every other program of the user, leave them room too
+    let suggested = (limit.unwrap_or(0) + dirs * 2).next_power_of_two();
+    format!(
+        "Ran out of inotify watches for {} of the {} directories below '{}' (fs.inotify.max_user_watches is {}), {}. \
+         Raising the limit (sudo sysctl fs.inotify.max_user_watches={}) and starting again watches them with inotify.",
+        unwatched,
+        dirs,
+        root.display(),
+        limit_text,
+        match polled {
+            true => "polling them instead",
+            false => "changes below them are missed",
+        },
+        suggested
+    )
+}
@@ -0,0 +1,198 @@
+//! Recording the raw notify events of a watch, and replaying them through the same pipeline later on
+//!
+//! A session is a JSON Lines file, one [`Recorded`] event per line with its paths relative to the root it happened
+//! below, so it can be replayed against a copy of the tree anywhere. Sessions are recorded and replayed against one
+//! root: paths outside of the first root stay absolute.
+//!
+//! ```text
+//! {"at_ms":0,"event":{"type":{"create":{"kind":"file"}},"paths":["4913"],"attrs":{}}}
+//! {"at_ms":9,"event":{"type":{"modify":{"kind":"rename","mode":"from"}},"paths":["a.txt"],"attrs":{"tracker":3998}}}
+//! ```
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use notify::{Config, EventHandler, RecursiveMode, WatcherKind};
+use serde::{Deserialize, Serialize};
+
+use crate::MagicError;
+
+/// One line of a session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Recorded {
+    /// Milliseconds since the first event of the session
+    pub at_ms: u64,
+    /// The event as notify reported it, but with relative paths
+    pub event: notify::Event,
+}
+
+/// Writes every event it is shown to a session file
+pub struct Recorder {
+    file: std::fs::File,
+    root: Option<PathBuf>,
+    start: Option<Instant>,
+}
+
+impl Recorder {
+    /// Record into `path` (overwriting it), the paths of events relative to `root`
+    pub fn create(path: &Path, root: Option<PathBuf>) -> Result<Recorder, MagicError> {
+        let file = std::fs::File::create(path)
+            .map_err(|err| format!("can not record to '{}': {}", path.display(), err))?;
+        Ok(Recorder {
+            file,
+            root,
+            start: None,
+        })
+    }
+
+    fn record(&mut self, event: &notify::Event) -> Result<(), MagicError> {
+        let mut event = event.clone();
+        if let Some(root) = &self.root {
+            for path in &mut event.paths {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    *path = relative.to_path_buf();
+                }
+            }
+        }
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let recorded = Recorded {
+            at_ms: start.elapsed().as_millis() as u64,
+            event,
+        };
+        // A line at a time, a recording cut short by Ctrl-C keeps everything up to then
+        writeln!(self.file, "{}", serde_json::to_string(&recorded)?)?;
+        Ok(())
+    }
+}
+
+/// Show every event `handler` gets to `recorder` first, if there is one
+pub fn recording(
+    mut handler: impl EventHandler,
+    recorder: Option<Arc<Mutex<Recorder>>>,
+) -> impl EventHandler {
+    move |res: Result<notify::Event, notify::Error>| {
+        if let (Some(recorder), Ok(event)) = (&recorder, &res) {
+            // Recording is a side job, the watch goes on without it
+            let _ = recorder.lock().unwrap().record(event);
+        }
+        handler.handle_event(res);
+    }
+}
+
+/// The events of a recorded session, in the order they happened
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    events: Vec<Recorded>,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Session, MagicError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("can not replay '{}': {}", path.display(), err))?;
+        Session::parse(&text)
+            .map_err(|err| format!("can not replay '{}': {}", path.display(), err).into())
+    }
+
+    pub fn parse(text: &str) -> Result<Session, MagicError> {
+        let mut events = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let recorded: Recorded = serde_json::from_str(line)
+                .map_err(|err| format!("line {}: {}", number + 1, err))?;
+            events.push(recorded);
+        }
+        Ok(Session { events })
+    }
+
+    pub fn events_ref(&self) -> &Vec<Recorded> {
+        &self.events
+    }
+
+    /// How long replaying takes, up to the last event
+    pub fn duration(&self) -> Duration {
+        let last = self.events.last().map_or(0, |recorded| recorded.at_ms);
+        Duration::from_millis(last)
+    }
+}
+
+/// A watcher that notices nothing, but tells its handler what a [`Session`] recorded, keeping the time between events
+///
+/// Replaying starts once the first root is watched, the relative paths of the session are taken to be below it.
+pub struct ReplayWatcher {
+    session: Arc<Session>,
+    handler: Arc<Mutex<dyn EventHandler>>,
+    stop: Option<Arc<AtomicBool>>,
+}
+
+impl ReplayWatcher {
+    pub fn new(session: Session, handler: impl EventHandler) -> ReplayWatcher {
+        ReplayWatcher {
+            session: Arc::new(session),
+            handler: Arc::new(Mutex::new(handler)),
+            stop: None,
+        }
+    }
+}
+
+fn replay(session: &Session, root: &Path, handler: &Mutex<dyn EventHandler>, stop: &AtomicBool) {
+    let start = Instant::now();
+    for recorded in session.events_ref() {
+        let at = start + Duration::from_millis(recorded.at_ms);
+        std::thread::sleep(at.saturating_duration_since(Instant::now()));
+        if stop.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut event = recorded.event.clone();
+        for path in &mut event.paths {
+            // Joining the empty path of the root itself would add a trailing slash
+            *path = match path.as_os_str().is_empty() {
+                true => root.to_path_buf(),
+                false => root.join(&path),
+            };
+        }
+        handler.lock().unwrap().handle_event(Ok(event));
+    }
+}
+
+impl notify::Watcher for ReplayWatcher {
+    /// A watcher replaying an empty session, see [`ReplayWatcher::new`]
+    fn new<F: EventHandler>(event_handler: F, _config: Config) -> notify::Result<Self> {
+        Ok(ReplayWatcher::new(Session::default(), event_handler))
+    }
+
+    fn watch(&mut self, path: &Path, _recursive_mode: RecursiveMode) -> notify::Result<()> {
+        if self.stop.is_some() {
+            return Ok(());
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = Some(stop.clone());
+
+        let session = self.session.clone();
+        let handler = self.handler.clone();
+        let root = path.to_path_buf();
+        std::thread::spawn(move || replay(&session, &root, &handler, &stop));
+        Ok(())
+    }
+
+    fn unwatch(&mut self, _path: &Path) -> notify::Result<()> {
+        if let Some(stop) = &self.stop {
+            stop.store(true, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn kind() -> WatcherKind {
+        WatcherKind::NullWatcher
+    }
+}
+
+impl Drop for ReplayWatcher {
+    fn drop(&mut self) {
+        let _ = notify::Watcher::unwatch(self, Path::new(""));
+    }
+}
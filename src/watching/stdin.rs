@@ -0,0 +1,105 @@
+//! What is piped in, watched like a file that keeps growing (`make 2>&1 | live_watch -`)
+
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{unbounded_channel, Sender, UnboundedReceiver, UnboundedSender};
+
+use crate::files::STDIN_KEY;
+use crate::messages::{ErrorSender, Loglet};
+use crate::watching::watcher_keep::WatcherUpdate;
+
+/// How long arriving output is gathered before the text is sent on, so a fast producer is not sent line by line
+const STDIN_BATCH: Duration = Duration::from_millis(100);
+
+/// Read stdin on a thread of its own (reading blocks), chunk by chunk as it arrives
+fn read_stdin(chunk_tx: UnboundedSender<Result<Vec<u8>, String>>) {
+    let mut stdin = std::io::stdin().lock();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let chunk = match stdin.read(&mut buffer) {
+            // Closed, the whole text was sent already
+            Ok(0) => return,
+            Ok(read) => Ok(buffer[..read].to_vec()),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => Err(format!("reading stdin failed: {}", err)),
+        };
+        let failed = chunk.is_err();
+        if chunk_tx.send(chunk).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// How many bytes at the start of `bytes` are whole characters, the last one may be cut off between two chunks
+fn complete_len(bytes: &[u8]) -> usize {
+    // A character starts at most three bytes before the end, continuation bytes start with 0b10
+    for (back, byte) in bytes.iter().rev().take(4).enumerate() {
+        let width = match byte.leading_ones() {
+            0 => 1,
+            width @ 2..=4 => width as usize,
+            _ => continue,
+        };
+        return match back + 1 < width {
+            true => bytes.len() - back - 1,
+            false => bytes.len(),
+        };
+    }
+    bytes.len()
+}
+
+/// Send what arrived as text added to [`STDIN_KEY`], once per batch of chunks
+async fn collect_stdin(
+    mut chunk_rx: UnboundedReceiver<Result<Vec<u8>, String>>,
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+) {
+    // Bytes not sent on yet, at most a cut off character once a batch is sent
+    let mut pending = Vec::new();
+    while let Some(chunk) = chunk_rx.recv().await {
+        let deadline = tokio::time::Instant::now() + STDIN_BATCH;
+        let mut chunk = Some(chunk);
+        while let Some(received) = chunk.take() {
+            match received {
+                Ok(bytes) => pending.extend(bytes),
+                Err(err) => {
+                    let _ = err_tx.send(Loglet::err_s(err)).await;
+                }
+            }
+            if let Ok(next) = tokio::time::timeout_at(deadline, chunk_rx.recv()).await {
+                chunk = next;
+            }
+        }
+
+        let complete: Vec<u8> = pending.drain(..complete_len(&pending)).collect();
+        if complete.is_empty() {
+            continue;
+        }
+        let text = String::from_utf8_lossy(&complete).into_owned();
+        let update = WatcherUpdate::FileAppend(PathBuf::from(STDIN_KEY), text);
+        if file_tx.send(update).await.is_err() {
+            return;
+        }
+    }
+    // Closed partway through a character, what is left is never going to be whole
+    if !pending.is_empty() {
+        let text = String::from_utf8_lossy(&pending).into_owned();
+        let update = WatcherUpdate::FileAppend(PathBuf::from(STDIN_KEY), text);
+        let _ = file_tx.send(update).await;
+    }
+}
+
+/// Watch stdin until it closes, the work is done on `rt` (and a thread blocked on reading)
+pub fn spawn_stdin_reader(
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    rt: Arc<Mutex<tokio::runtime::Runtime>>,
+) {
+    let (chunk_tx, chunk_rx) = unbounded_channel();
+    std::thread::spawn(move || read_stdin(chunk_tx));
+    crate::force_am_once(rt, |rt| {
+        rt.spawn(collect_stdin(chunk_rx, file_tx, err_tx));
+    });
+}
@@ -0,0 +1,258 @@
+//! The handle to a running watch
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tokio::sync::mpsc::{
+    channel, unbounded_channel, Receiver, Sender, UnboundedReceiver, UnboundedSender,
+};
+
+use crate::files::{self, FileStore, STDIN_KEY};
+use crate::ignore_rules::IgnoreRules;
+use crate::list_filters::{ListFilters, SizeLimit};
+use crate::messages::Loglet;
+use crate::watching::activity::Activity;
+use crate::watching::backend::{self, Backends, MakeFallback, WatchBackend, WatchedDirs};
+use crate::watching::session::{self, Recorder};
+use crate::watching::stdin;
+use crate::watching::watcher_keep::{self, WatcherUpdate};
+use crate::MagicError;
+
+/// How a [`Watcher`] watches, the defaults are what live_watch uses without arguments
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    /// Gitignore style globs left out on top of the defaults, ignore files and config file
+    pub ignore_globs: Vec<String>,
+    /// How long the tree has to stay quiet before changed paths are sent as one [`WatchEvent::Changes`]
+    pub debounce: Duration,
+    /// What notices changes on disk, decided once when the watch starts
+    pub backend: WatchBackend,
+    /// How often [`WatchBackend::Poll`] scans the tree
+    pub poll_interval: Duration,
+    /// Record the raw events of the watch to this session file, see [`session`]
+    pub record: Option<PathBuf>,
+    /// Replay this session file instead of watching the disk, whatever [`WatchOptions::backend`] says
+    pub replay: Option<PathBuf>,
+    /// Watch what is piped in too, as the file [`STDIN_KEY`]
+    pub stdin: bool,
+}
+
+impl WatchOptions {
+    pub fn new() -> Self {
+        WatchOptions {
+            ignore_globs: Vec::new(),
+            debounce: watcher_keep::DEBOUNCE_QUIET,
+            backend: WatchBackend::Auto,
+            poll_interval: backend::POLL_INTERVAL,
+            record: None,
+            replay: None,
+            stdin: false,
+        }
+    }
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Everything a [`Watcher`] has to say
+///
+/// Events of one kind arrive in the order they happened, events of different kinds may overtake each other.
+pub enum WatchEvent {
+    /// New content, a rename or a delete, for [`FileStore::apply`]
+    Update(WatcherUpdate),
+    /// A filesystem event as notify reported it, reads included
+    Activity(Activity),
+    /// Every path that changed since the last batch, once the tree was quiet for [`WatchOptions::debounce`]
+    Changes(Vec<PathBuf>),
+    /// Something went wrong along the way (a file that could not be read), watching goes on
+    Error(Loglet),
+}
+
+/// Watches a set of roots (recursively) until dropped, streaming what happens as [`WatchEvent`]s
+pub struct Watcher {
+    roots: Vec<PathBuf>,
+    backend: WatchBackend,
+    watched_dirs: Arc<Mutex<WatchedDirs>>,
+    stdin: bool,
+    ignore_rules: Arc<IgnoreRules>,
+    size_limit: SizeLimit,
+    path_tx: Sender<PathBuf>,
+    event_rx: UnboundedReceiver<WatchEvent>,
+}
+
+/// Send everything `rx` receives on as events, until either side is gone
+async fn forward<T>(
+    mut rx: Receiver<T>,
+    event_tx: UnboundedSender<WatchEvent>,
+    event: fn(T) -> WatchEvent,
+) {
+    while let Some(received) = rx.recv().await {
+        if event_tx.send(event(received)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Like [`forward`], for the channels that never make their sender wait
+async fn forward_unbounded<T>(
+    mut rx: UnboundedReceiver<T>,
+    event_tx: UnboundedSender<WatchEvent>,
+    event: fn(T) -> WatchEvent,
+) {
+    while let Some(received) = rx.recv().await {
+        if event_tx.send(event(received)).is_err() {
+            return;
+        }
+    }
+}
+
+impl Watcher {
+    /// Start watching `roots` (absolute paths, files or directories), the work is done on `rt`
+    ///
+    /// Fails on ignore globs that make no sense, when the backend can not be started and on sessions that can not be
+    /// recorded or replayed. Broken ignore files only end up as [`WatchEvent::Error`]s.
+    pub fn new(
+        roots: &[PathBuf],
+        options: &WatchOptions,
+        rt: Arc<Mutex<tokio::runtime::Runtime>>,
+    ) -> Result<Watcher, MagicError> {
+        let ignore_rules = Arc::new(IgnoreRules::new(roots, &options.ignore_globs)?);
+        let (event_tx, event_rx) = unbounded_channel();
+        for err in ignore_rules.errors_ref() {
+            let _ = event_tx.send(WatchEvent::Error(Loglet::err_s(err)));
+        }
+
+        let (file_tx, file_rx) = channel(32);
+        let (err_tx, err_rx) = channel(32);
+        let (activity_tx, activity_rx) = unbounded_channel();
+        let (change_tx, change_rx) = unbounded_channel();
+        let (batch_tx, batch_rx) = unbounded_channel();
+        let (path_tx, path_rx) = channel(32);
+
+        let backend = match options.replay {
+            Some(_) => WatchBackend::Replay,
+            None => options.backend.resolve(roots),
+        };
+        let recorder = match &options.record {
+            Some(path) => Some(Arc::new(Mutex::new(Recorder::create(
+                path,
+                roots.first().cloned(),
+            )?))),
+            None => None,
+        };
+        let size_limit = SizeLimit::new();
+        let actions = || {
+            let actions = watcher_keep::watcher_actions(
+                file_tx.clone(),
+                err_tx.clone(),
+                activity_tx.clone(),
+                change_tx.clone(),
+                ignore_rules.clone(),
+                size_limit.clone(),
+                rt.clone(),
+            );
+            session::recording(actions, recorder.clone())
+        };
+        let watcher = backend::create_watcher(backend, options, actions())?;
+        // Standing by for the roots inotify runs out of watches for
+        let make_fallback: Option<MakeFallback> = match backend {
+            WatchBackend::Inotify => {
+                let (options, actions) = (options.clone(), actions());
+                Some(Box::new(move || {
+                    backend::create_watcher(WatchBackend::Poll, &options, actions)
+                }))
+            }
+            WatchBackend::Poll | WatchBackend::Auto | WatchBackend::Replay => None,
+        };
+        let watched_dirs = Arc::new(Mutex::new(WatchedDirs::default()));
+        let backends = Backends::new(watcher, make_fallback, watched_dirs.clone());
+        if options.stdin {
+            stdin::spawn_stdin_reader(file_tx, err_tx.clone(), rt.clone());
+        }
+        watcher_keep::spawn_change_debouncer(change_rx, batch_tx, options.debounce, rt.clone());
+        watcher_keep::spawn_watcher_thread(backends, roots.to_vec(), path_rx, err_tx, rt.clone());
+
+        crate::force_am_once(rt, |rt| {
+            rt.spawn(forward(file_rx, event_tx.clone(), WatchEvent::Update));
+            rt.spawn(forward(err_rx, event_tx.clone(), WatchEvent::Error));
+            rt.spawn(forward_unbounded(
+                activity_rx,
+                event_tx.clone(),
+                WatchEvent::Activity,
+            ));
+            rt.spawn(forward_unbounded(batch_rx, event_tx, WatchEvent::Changes));
+        });
+
+        Ok(Watcher {
+            roots: roots.to_vec(),
+            backend,
+            watched_dirs,
+            stdin: options.stdin,
+            ignore_rules,
+            size_limit,
+            path_tx,
+            event_rx,
+        })
+    }
+
+    /// The roots the watch started out with
+    pub fn roots_ref(&self) -> &Vec<PathBuf> {
+        &self.roots
+    }
+
+    /// The backend doing the watching, never [`WatchBackend::Auto`]
+    pub fn backend(&self) -> WatchBackend {
+        self.backend
+    }
+
+    /// How many directories are watched, and how many of them are polled since inotify ran out of watches
+    pub fn watched_dirs(&self) -> WatchedDirs {
+        *self.watched_dirs.lock().unwrap()
+    }
+
+    pub fn ignore_rules_clone(&self) -> Arc<IgnoreRules> {
+        self.ignore_rules.clone()
+    }
+
+    /// A store of the files below the roots, listed with the same ignore rules the watcher uses
+    ///
+    /// When stdin is watched it is listed from the start, empty until something arrives. The size limit of the
+    /// store's filters is shared with the watcher, which leaves files over it unread.
+    pub fn file_store(&self, filters: ListFilters) -> FileStore {
+        let mut store = FileStore::new(&self.roots, self.ignore_rules_clone(), filters);
+        store.share_size_limit(self.size_limit.clone());
+        if self.stdin {
+            let piped = files::File::from_text(PathBuf::from(STDIN_KEY), String::new());
+            store
+                .file_cache_mut()
+                .insert(PathBuf::from(STDIN_KEY), Some(piped));
+        }
+        store
+    }
+
+    /// Provide a sender switching the watch over to another root, see [`FileStore::switch_roots`]
+    pub fn path_tx_clone(&self) -> Sender<PathBuf> {
+        self.path_tx.clone()
+    }
+
+    /// The next event, if one is waiting, for frame loops that can not wait
+    pub fn try_next(&mut self) -> Option<WatchEvent> {
+        let waker = futures::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        match self.event_rx.poll_recv(&mut cx) {
+            Poll::Ready(event) => event,
+            Poll::Pending => None,
+        }
+    }
+
+    /// Wait for the next event
+    pub async fn next(&mut self) -> Option<WatchEvent> {
+        self.event_rx.recv().await
+    }
+}
@@ -0,0 +1,448 @@
+use crate::files;
+use crate::ignore_rules::IgnoreRules;
+use crate::list_filters::SizeLimit;
+use crate::messages::ErrorSender;
+use crate::messages::Loglet;
+use crate::watching::activity::{Activity, ActivitySender};
+use crate::watching::backend::Backends;
+
+use notify::event::EventKind::*;
+use notify::event::{MetadataKind, ModifyKind, RenameMode};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use walkdir::WalkDir;
+
+pub struct RenameEvent {
+    from: PathBuf,
+    to: PathBuf,
+}
+
+impl RenameEvent {
+    fn get_from_ref(&self) -> &PathBuf {
+        &self.from
+    }
+    fn get_to_ref(&self) -> &PathBuf {
+        &self.to
+    }
+}
+
+impl RenameEvent {
+    fn new(from: impl Into<PathBuf>, to: impl Into<PathBuf>) -> RenameEvent {
+        let from = from.into();
+        let to = to.into();
+        RenameEvent { from, to }
+    }
+
+    pub fn from_and_to_ref(&self) -> (&PathBuf, &PathBuf) {
+        (self.get_from_ref(), self.get_to_ref())
+    }
+}
+
+pub enum WatcherUpdate {
+    FileContent(files::File),
+    FileRename(RenameEvent),
+    FileDelete(PathBuf),
+    /// New content, too big to be read unless the file is opened already
+    FileTooBig(PathBuf),
+    /// Text added to the end of a file, what is piped in only ever grows that way
+    FileAppend(PathBuf, String),
+}
+
+impl WatcherUpdate {
+    fn new_content(file: files::File) -> WatcherUpdate {
+        WatcherUpdate::FileContent(file)
+    }
+
+    fn new_rename(from: PathBuf, to: PathBuf) -> WatcherUpdate {
+        WatcherUpdate::FileRename(RenameEvent::new(from, to))
+    }
+
+    fn new_delete(deleted_path: PathBuf) -> WatcherUpdate {
+        WatcherUpdate::FileDelete(deleted_path)
+    }
+}
+
+// pub type WatcherUpdate = files::File;
+async fn load_file(
+    file_path: PathBuf,
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    size_limit: &SizeLimit,
+) {
+    // Files over the size limit are not even read, the store reads the ones that are opened itself
+    let size = std::fs::metadata(&file_path).map(|metadata| metadata.len());
+    if size.is_ok_and(|size| !size_limit.loads(size)) {
+        let watcher_update = WatcherUpdate::FileTooBig(file_path);
+        file_tx.send(watcher_update).await.unwrap();
+        return;
+    }
+    // let file = files::File::load_file(&file_path).map_err(|err| Loglet::err(err));
+    let result = files::File::load_file(&file_path).map_err(|err| Loglet::err(err));
+
+    // keep
+    match result {
+        Ok(file) => file_tx
+            .send(WatcherUpdate::new_content(file))
+            .await
+            .unwrap(),
+        Err(err) => err_tx.send(err).await.unwrap(),
+    }
+}
+
+/// Load a file that appeared, or every file inside a directory that appeared (moved in from elsewhere)
+async fn load_path(
+    path: PathBuf,
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    ignore_rules: &IgnoreRules,
+    size_limit: &SizeLimit,
+) {
+    if path.is_dir() {
+        let files = WalkDir::new(&path)
+            .into_iter()
+            .filter_entry(|entry| {
+                !ignore_rules.is_ignored(entry.path(), entry.file_type().is_dir())
+            })
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file());
+        for entry in files {
+            let path = entry.into_path();
+            load_file(path, file_tx.clone(), err_tx.clone(), size_limit).await;
+        }
+    } else if path.is_file() {
+        load_file(path, file_tx, err_tx, size_limit).await;
+    }
+}
+
+async fn delete_path(path: PathBuf, file_tx: Sender<WatcherUpdate>) {
+    file_tx.send(WatcherUpdate::new_delete(path)).await.unwrap();
+}
+
+/// For events that do not say what happened: whatever still exists is (re)loaded, the rest is gone
+async fn reconcile_paths(
+    effected_paths: Vec<PathBuf>,
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    ignore_rules: Arc<IgnoreRules>,
+    size_limit: SizeLimit,
+) {
+    for path_buf in effected_paths {
+        match path_buf.exists() {
+            true => {
+                let (file_tx, err_tx) = (file_tx.clone(), err_tx.clone());
+                load_path(path_buf, file_tx, err_tx, &ignore_rules, &size_limit).await
+            }
+            false => delete_path(path_buf, file_tx.clone()).await,
+        }
+    }
+}
+
+fn send_watcher_update(
+    future: impl std::future::Future<Output = ()> + Send + 'static,
+    // effected_paths: Vec<PathBuf>,
+    rt: Arc<Mutex<tokio::runtime::Runtime>>,
+    // watcher_sender: Sender<WatcherUpdate>,
+    // err_sender: ErrorSender,
+) {
+    loop {
+        if let Ok(rt) = rt.try_lock() {
+            rt.spawn(future);
+            break;
+        }
+    }
+}
+
+/// How long the first half of a rename waits for its other half, before it counts as moving out of the tree
+const RENAME_PAIRING: Duration = Duration::from_millis(100);
+
+/// Renames by notify tracker: whether the `Both` event pairing its `From` and `To` arrived yet
+///
+/// inotify reports a rename inside the tree three times (`From`, `To`, then `Both`), only `Both` is acted on.
+type PendingRenames = Arc<Mutex<HashMap<usize, bool>>>;
+
+fn on_modify_event(
+    event: notify::Event,
+    pending_renames: PendingRenames,
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    ignore_rules: Arc<IgnoreRules>,
+    size_limit: SizeLimit,
+    rt_clone: Arc<Mutex<tokio::runtime::Runtime>>,
+) {
+    let Modify(mod_kind) = event.kind else {
+        return;
+    };
+    let tracker = event.attrs.tracker();
+    let effected_paths = event.paths;
+    match mod_kind {
+        ModifyKind::Name(rename) => match rename {
+            // Moved out of the watched tree, unless the other half of the rename turns up
+            notify::event::RenameMode::From => {
+                let paired = tracker.and_then(|tracker| {
+                    let mut pending = pending_renames.lock().unwrap();
+                    // Seen before: a rename into an ignored path, made a `From` by `drop_ignored_paths`
+                    let seen = pending.contains_key(&tracker);
+                    pending.insert(tracker, false);
+                    (!seen).then_some(tracker)
+                });
+                let the_future = async move {
+                    if let Some(tracker) = paired {
+                        tokio::time::sleep(RENAME_PAIRING).await;
+                        let pending = pending_renames.lock().unwrap().remove(&tracker);
+                        if pending == Some(true) {
+                            return;
+                        }
+                    }
+                    for path_buf in effected_paths {
+                        delete_path(path_buf, file_tx.clone()).await;
+                    }
+                };
+                send_watcher_update(the_future, rt_clone);
+            }
+
+            // Moved into the watched tree
+            notify::event::RenameMode::To => {
+                let paired = tracker
+                    .is_some_and(|tracker| pending_renames.lock().unwrap().contains_key(&tracker));
+                if paired {
+                    return;
+                }
+                let the_future = async move {
+                    for path_buf in effected_paths {
+                        let (file_tx, err_tx) = (file_tx.clone(), err_tx.clone());
+                        load_path(path_buf, file_tx, err_tx, &ignore_rules, &size_limit).await;
+                    }
+                };
+                send_watcher_update(the_future, rt_clone);
+            }
+
+            notify::event::RenameMode::Both => {
+                if let Some(tracker) = tracker {
+                    if let Some(paired) = pending_renames.lock().unwrap().get_mut(&tracker) {
+                        *paired = true;
+                    }
+                }
+                let the_future = async move {
+                    let from = effected_paths.first().unwrap().to_owned();
+                    let to = effected_paths.get(1).unwrap().to_owned();
+                    let watcher_update = WatcherUpdate::new_rename(from, to.clone());
+                    file_tx.send(watcher_update).await.unwrap();
+
+                    // Editors save by renaming a temporary file over the real one, so the content is new as well
+                    if to.is_file() {
+                        load_file(to, file_tx, err_tx, &size_limit).await;
+                    }
+                };
+                send_watcher_update(the_future, rt_clone);
+            }
+
+            _ => {
+                let the_future =
+                    reconcile_paths(effected_paths, file_tx, err_tx, ignore_rules, size_limit);
+                send_watcher_update(the_future, rt_clone);
+            }
+        },
+
+        // The polling backend tells a newer modification time apart from new content with the same time
+        ModifyKind::Data(_) | ModifyKind::Metadata(MetadataKind::WriteTime) => {
+            let the_future = async move {
+                for path_buf in effected_paths {
+                    load_file(path_buf, file_tx.clone(), err_tx.clone(), &size_limit).await;
+                }
+            };
+            send_watcher_update(the_future, rt_clone);
+        }
+        _ => {}
+    }
+
+    // if let ModifyKind::Name(rename) = mod_kind {
+    //     match rename {
+    //         notify::event::RenameMode::From => {}
+
+    //         notify::event::RenameMode::To => {}
+
+    //         notify::event::RenameMode::Both => {
+    //             let the_future = async move {
+    //                 let from = effected_paths.get(0).unwrap().to_owned();
+    //                 let to = effected_paths.get(1).unwrap().to_owned();
+    //                 let watcher_update = WatcherUpdate::new_rename(from, to);
+    //                 file_tx.send(watcher_update).await.unwrap();
+    //             };
+    //             send_watcher_update(the_future, rt_clone.clone());
+    //         }
+
+    //         _ => panik(),
+    //     }
+    // }
+
+    // if let ModifyKind::Data(_data_change) = mod_kind {
+    //     println!(
+    //         "Modification occured: Here is the list of affected paths: <{:?}>",
+    //         effected_paths
+    //     );
+    //     let the_future = async move {
+    //         for path_buf in effected_paths {
+    //             load_file(path_buf, file_tx.clone(), err_tx.clone()).await;
+    //         }
+    //     };
+    //     send_watcher_update(the_future, rt_clone);
+    // }
+}
+
+/// Paths touched by one filesystem event, before debouncing
+pub type ChangeSender = UnboundedSender<Vec<PathBuf>>;
+
+/// How long the tree has to stay quiet (unless told otherwise) before the changes so far are passed on as one batch
+pub const DEBOUNCE_QUIET: Duration = Duration::from_millis(300);
+
+/// Collect changed paths until nothing changed for `quiet`, then send them (once each) as one batch
+async fn debounce_changes(
+    mut change_rx: UnboundedReceiver<Vec<PathBuf>>,
+    batch_tx: UnboundedSender<Vec<PathBuf>>,
+    quiet: Duration,
+) {
+    while let Some(mut batch) = change_rx.recv().await {
+        while let Ok(Some(paths)) = tokio::time::timeout(quiet, change_rx.recv()).await {
+            batch.extend(paths);
+        }
+        batch.sort();
+        batch.dedup();
+        if batch_tx.send(batch).is_err() {
+            return;
+        }
+    }
+}
+
+pub fn spawn_change_debouncer(
+    change_rx: UnboundedReceiver<Vec<PathBuf>>,
+    batch_tx: UnboundedSender<Vec<PathBuf>>,
+    quiet: Duration,
+    rt: Arc<Mutex<tokio::runtime::Runtime>>,
+) {
+    send_watcher_update(debounce_changes(change_rx, batch_tx, quiet), rt);
+}
+
+/// Drop the ignored paths of `event`, returns false when none are left
+fn drop_ignored_paths(event: &mut notify::Event, ignore_rules: &IgnoreRules) -> bool {
+    let ignored: Vec<bool> = event
+        .paths
+        .iter()
+        .map(|path| ignore_rules.is_ignored_path(path))
+        .collect();
+    // Renaming between an ignored and a watched path moves a file into or out of the watched part of the tree
+    if let Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+        match (ignored.first(), ignored.get(1)) {
+            (Some(true), Some(false)) => event.kind = Modify(ModifyKind::Name(RenameMode::To)),
+            (Some(false), Some(true)) => event.kind = Modify(ModifyKind::Name(RenameMode::From)),
+            _ => {}
+        }
+    }
+    let mut ignored = ignored.into_iter();
+    event.paths.retain(|_| !ignored.next().unwrap_or(false));
+
+    !event.paths.is_empty()
+}
+
+/// The event handler of every backend, turning what notify noticed into updates, activity and changes
+pub fn watcher_actions(
+    file_tx: Sender<WatcherUpdate>,
+    err_tx: ErrorSender,
+    activity_tx: ActivitySender,
+    change_tx: ChangeSender,
+    ignore_rules: Arc<IgnoreRules>,
+    size_limit: SizeLimit,
+    rt: Arc<Mutex<tokio::runtime::Runtime>>,
+) -> impl notify::EventHandler {
+    let pending_renames = PendingRenames::default();
+    move |res: Result<notify::Event, notify::Error>| {
+        let rt_clone = rt.clone();
+        let result = || -> Result<(), notify::Error> {
+            let mut event = res?;
+            // Nothing downstream (cache, activity feed, triggers) ever hears about ignored paths
+            if !drop_ignored_paths(&mut event, &ignore_rules) {
+                return Ok(());
+            }
+            // The feed only goes away with the app
+            let _ = activity_tx.send(Activity::from_event(&event));
+            // Reads are not changes, and a triggered command reading the tree would otherwise trigger itself
+            if !matches!(event.kind, Access(_)) {
+                let _ = change_tx.send(event.paths.clone());
+            }
+            match event.kind {
+                Modify(_) => on_modify_event(
+                    event,
+                    pending_renames.clone(),
+                    file_tx.clone(),
+                    err_tx.clone(),
+                    ignore_rules.clone(),
+                    size_limit.clone(),
+                    rt_clone,
+                ),
+                Create(_create_kind) => {
+                    let effected_paths = event.paths;
+                    let file_tx = file_tx.clone();
+                    let err_tx = err_tx.clone();
+                    let ignore_rules = ignore_rules.clone();
+                    let size_limit = size_limit.clone();
+                    let the_future = async move {
+                        for path_buf in effected_paths {
+                            let (file_tx, err_tx) = (file_tx.clone(), err_tx.clone());
+                            load_path(path_buf, file_tx, err_tx, &ignore_rules, &size_limit).await;
+                        }
+                    };
+                    send_watcher_update(the_future, rt_clone);
+                }
+                // Nothing to reload, these only show up in the activity feed
+                Access(_access_kind) => {}
+                Remove(_remove_kind) => {
+                    let effected_paths = event.paths;
+                    let file_tx = file_tx.clone();
+                    let the_future = async move {
+                        for path_buf in effected_paths {
+                            delete_path(path_buf, file_tx.clone()).await;
+                        }
+                    };
+                    send_watcher_update(the_future, rt_clone);
+                }
+                Any | Other => {}
+            }
+
+            Ok(())
+        }();
+        // A directory the polling backend could not read, or inotify losing events, watching goes on
+        if let Err(err) = result {
+            let _ = err_tx.try_send(Loglet::err_s(err.to_string()));
+        }
+    }
+}
+
+async fn watch_path(backends: &mut Backends, path: &Path, err_sender: &Sender<Loglet>) {
+    if let Some(msg) = backends.watch(path) {
+        err_sender.send(Loglet::err_s(msg)).await.unwrap();
+    }
+}
+
+/// Watch every root, switching over to whatever path is sent over `path_rx`
+pub fn spawn_watcher_thread(
+    mut backends: Backends,
+    roots: Vec<PathBuf>,
+    mut path_rx: Receiver<PathBuf>,
+    err_sender: Sender<Loglet>,
+    rt_am: Arc<Mutex<tokio::runtime::Runtime>>,
+) {
+    crate::force_am_once(rt_am, |rt| {
+        rt.spawn(async move {
+            for root in &roots {
+                watch_path(&mut backends, root, &err_sender).await;
+            }
+            while let Some(path) = path_rx.recv().await {
+                // Switching roots, nothing happening below the old ones matters anymore
+                backends.unwatch_all();
+                watch_path(&mut backends, &path, &err_sender).await;
+            }
+        });
+    });
+}
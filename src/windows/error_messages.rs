@@ -1,12 +1,10 @@
+use crate::messages::{ErrorReceiver, ErrorSender, Loglet};
 use crate::time_of_day;
-use crate::windows::generic_windows::{GenericWindow, Loglet};
+use crate::windows::generic_windows::GenericWindow;
 use core::panic;
 use std::task::Context;
 use std::task::Poll;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
-
-pub type ErrorSender = Sender<Loglet>;
-pub type ErrorReceiver = Receiver<Loglet>;
+use tokio::sync::mpsc::{channel, Sender};
 
 pub struct ErrorMessage {
     pub display: GenericWindow,
@@ -50,6 +48,11 @@ impl ErrorMessage {
         self.sender.clone()
     }
 
+    pub fn push_loglet(&mut self, loglet: Loglet) {
+        //! Sync appending of a message that arrived some other way than the sender
+        GenericWindow::push_loglet(&mut self.display, loglet);
+    }
+
     pub fn push_err(&mut self, msg: &str) {
         // Sync err appending, for sending errors on the main thread
         let loglet = Loglet::new("Error", msg, &time_of_day());
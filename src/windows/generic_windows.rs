@@ -1,7 +1,7 @@
 use crate::{
     eframe_tools::{scroll_and_vert, space_vert},
+    messages::Loglet,
     string_tools::*,
-    time_of_day,
 };
 use eframe::egui::{Context, Ui};
 
@@ -125,48 +125,3 @@ impl MessageLog {
         self.log.push(loglet)
     }
 }
-
-#[derive(Debug)]
-pub struct Loglet {
-    kind: String,
-    msg: String,
-    time: String,
-}
-
-impl Loglet {
-    pub fn new(kind: &str, msg: &str, time: &str) -> Loglet {
-        Self {
-            kind: kind.to_string(),
-            msg: msg.to_string(),
-            time: time.to_string(),
-        }
-    }
-    pub fn err_s(msg: impl Into<String>) -> Loglet {
-        Self {
-            kind: "Error".to_string(),
-            msg: msg.into(),
-            time: time_of_day(),
-        }
-    }
-    pub fn err(err: crate::MagicError) -> Loglet {
-        Self {
-            kind: "Error".to_string(),
-            msg: err.to_string(),
-            time: time_of_day(),
-        }
-    }
-
-    pub fn format_loglet(&self) -> String {
-        let lyne = |elem: &String| -> String {
-            format!("{}{}{}{}", newliner(1), tabber(1), elem, newliner(2))
-        };
-        let lyne_2 = "/================================================/";
-        format!(
-            "Kind:{}Message:{}Time:{}{}",
-            lyne(&self.kind),
-            lyne(&self.msg),
-            lyne(&self.time),
-            lyne_2
-        )
-    }
-}
@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use live_watch::files::FileStore;
+use live_watch::list_filters::ListFilters;
+use live_watch::watching::backend::{Backends, WatchBackend, WatchedDirs};
+use live_watch::watching::watcher::{WatchEvent, WatchOptions, Watcher};
+use live_watch::WatcherX;
+
+mod common;
+use common::TempDir;
+
+type Runtime = Arc<Mutex<tokio::runtime::Runtime>>;
+
+/// A running watch of a fresh directory `w` holding `a.txt`, and the store kept up to date with it
+struct Watched {
+    root: PathBuf,
+    rt: Runtime,
+    watcher: Watcher,
+    store: FileStore,
+    // Last, so the watcher is gone before the tree
+    temp: TempDir,
+}
+
+impl Watched {
+    fn new(test: &str, backend: WatchBackend) -> Watched {
+        let temp = TempDir::new(&format!("{}_{:?}", test, backend));
+        let root = temp.tree("w", &[("a.txt", "hello\n")]);
+
+        let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
+        let mut options = WatchOptions::new();
+        options.backend = backend;
+        options.poll_interval = Duration::from_millis(50);
+        let watcher = Watcher::new(std::slice::from_ref(&root), &options, rt.clone()).unwrap();
+        assert_eq!(watcher.backend(), backend);
+        let store = watcher.file_store(ListFilters::new());
+        // Give the watcher a moment to start watching (and the poller to take its first look)
+        std::thread::sleep(Duration::from_millis(300));
+
+        Watched {
+            root,
+            rt,
+            watcher,
+            store,
+            temp,
+        }
+    }
+
+    fn text(&self, key: &str) -> Option<String> {
+        let file = self.store.file_cache_ref().file_ref(Path::new(key))?;
+        Some(file.file_form_ref().text_ref().to_owned())
+    }
+
+    /// Apply updates until `done` says the store is where it should be, or five seconds passed
+    fn wait_until(&mut self, what: &str, done: impl Fn(&Watched) -> bool) {
+        let handle = self.rt.lock().unwrap().handle().clone();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(self) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            assert!(!left.is_zero(), "never saw {}", what);
+            let next = handle.block_on(async {
+                // The timer has to be made inside the runtime
+                tokio::time::timeout(left, self.watcher.next()).await
+            });
+            if let Ok(Some(WatchEvent::Update(update))) = next {
+                self.store.apply(update);
+            }
+        }
+    }
+}
+
+fn sees_edits(backend: WatchBackend) {
+    let mut watched = Watched::new("edits", backend);
+    assert_eq!(watched.text("w/a.txt").as_deref(), Some("hello\n"));
+
+    std::fs::write(watched.root.join("a.txt"), "hello\nworld\n").unwrap();
+    watched.wait_until("the edit", |watched| {
+        watched.text("w/a.txt").as_deref() == Some("hello\nworld\n")
+    });
+}
+
+fn sees_files_come_and_go(backend: WatchBackend) {
+    let mut watched = Watched::new("come_and_go", backend);
+
+    std::fs::write(watched.root.join("b.txt"), "new\n").unwrap();
+    watched.wait_until("the new file", |watched| {
+        watched.text("w/b.txt").as_deref() == Some("new\n")
+    });
+
+    std::fs::remove_file(watched.root.join("a.txt")).unwrap();
+    watched.wait_until("the delete", |watched| {
+        !watched
+            .store
+            .file_cache_ref()
+            .contains(Path::new("w/a.txt"))
+    });
+}
+
+fn sees_renames(backend: WatchBackend) {
+    let mut watched = Watched::new("renames", backend);
+
+    std::fs::rename(watched.root.join("a.txt"), watched.root.join("c.txt")).unwrap();
+    watched.wait_until("the rename", |watched| {
+        let cache = watched.store.file_cache_ref();
+        !cache.contains(Path::new("w/a.txt"))
+            && watched.text("w/c.txt").as_deref() == Some("hello\n")
+    });
+}
+
+fn sees_new_directories(backend: WatchBackend) {
+    let mut watched = Watched::new("directories", backend);
+
+    let dir = watched.root.join("sub");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::write(dir.join("d.txt"), "inside\n").unwrap();
+    watched.wait_until("the file in the new directory", |watched| {
+        watched.text("w/sub/d.txt").as_deref() == Some("inside\n")
+    });
+}
+
+fn leaves_big_files_unloaded(backend: WatchBackend) {
+    let mut watched = Watched::new("big_files", backend);
+    let mut filters = ListFilters::new();
+    filters.set_max_file_size(Some(10));
+    watched.store.set_filters(filters);
+
+    // Moved in whole, written in place it would be loaded while it is still empty
+    let outside = watched.temp.dir.join("b.txt");
+    std::fs::write(&outside, "too big to load\n").unwrap();
+    std::fs::rename(&outside, watched.root.join("b.txt")).unwrap();
+    watched.wait_until("the big file", |watched| {
+        watched
+            .store
+            .file_cache_ref()
+            .contains(Path::new("w/b.txt"))
+    });
+    assert_eq!(watched.text("w/b.txt"), None);
+
+    // Loaded before, so it stays loaded whatever its size
+    std::fs::write(watched.root.join("a.txt"), "hello\nworld, and more\n").unwrap();
+    watched.wait_until("the grown file", |watched| {
+        watched.text("w/a.txt").as_deref() == Some("hello\nworld, and more\n")
+    });
+}
+
+fn counts_watched_dirs(backend: WatchBackend) {
+    let watched = Watched::new("counts", backend);
+
+    let expected = WatchedDirs {
+        watched: 1,
+        polled: 0,
+    };
+    assert_eq!(watched.watcher.watched_dirs(), expected);
+}
+
+/// The same scenarios, once for every backend
+macro_rules! for_each_backend {
+    ($($scenario:ident),*) => {
+        mod inotify {
+            use super::*;
+            $(#[test]
+            fn $scenario() {
+                super::$scenario(WatchBackend::Inotify);
+            })*
+        }
+
+        mod poll {
+            use super::*;
+            $(#[test]
+            fn $scenario() {
+                super::$scenario(WatchBackend::Poll);
+            })*
+        }
+    };
+}
+
+for_each_backend!(
+    sees_edits,
+    sees_files_come_and_go,
+    sees_renames,
+    sees_new_directories,
+    leaves_big_files_unloaded,
+    counts_watched_dirs
+);
+
+/// Stands in for inotify with room for `limit` watches, or for the poller when there is no limit
+///
+/// Like inotify it watches directory after directory and keeps what it got when it runs out.
+struct LimitedWatcher {
+    limit: usize,
+    watched: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl notify::Watcher for LimitedWatcher {
+    fn new<F: notify::EventHandler>(_handler: F, _config: notify::Config) -> notify::Result<Self> {
+        Ok(LimitedWatcher {
+            limit: usize::MAX,
+            watched: Arc::default(),
+        })
+    }
+
+    fn watch(&mut self, path: &Path, _recursive_mode: notify::RecursiveMode) -> notify::Result<()> {
+        let dirs = walkdir::WalkDir::new(path)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_dir());
+        let mut watched = self.watched.lock().unwrap();
+        for dir in dirs {
+            if watched.len() == self.limit {
+                let err = notify::Error::new(notify::ErrorKind::MaxFilesWatch);
+                return Err(err.add_path(dir.into_path()));
+            }
+            watched.push(dir.into_path());
+        }
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> notify::Result<()> {
+        self.watched
+            .lock()
+            .unwrap()
+            .retain(|dir| !dir.starts_with(path));
+        Ok(())
+    }
+
+    fn kind() -> notify::WatcherKind {
+        notify::WatcherKind::NullWatcher
+    }
+}
+
+/// What came of watching a tree of seven directories with room for `limit` inotify watches
+struct LimitedWatch {
+    _temp: TempDir,
+    root: PathBuf,
+    message: Option<String>,
+    watched_dirs: WatchedDirs,
+    // None when no poller was made
+    polled: Option<Vec<PathBuf>>,
+}
+
+fn watch_with_limit(test: &str, limit: usize) -> LimitedWatch {
+    let temp = TempDir::new(&format!("limit_{}", test));
+    let root = temp.tree("w", &[]);
+    for dir in ["a/a1", "a/a2", "b/b1", "c"] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+    }
+
+    let watcher = LimitedWatcher {
+        limit,
+        watched: Arc::default(),
+    };
+    let polled = Arc::new(Mutex::new(Vec::new()));
+    let poller = LimitedWatcher {
+        limit: usize::MAX,
+        watched: polled.clone(),
+    };
+    let made = Arc::new(AtomicBool::new(false));
+    let make_poller = made.clone();
+    let watched_dirs = Arc::new(Mutex::new(WatchedDirs::default()));
+    let mut backends = Backends::new(
+        Box::new(watcher),
+        Some(Box::new(move || {
+            make_poller.store(true, Ordering::Relaxed);
+            Ok(Box::new(poller) as WatcherX)
+        })),
+        watched_dirs.clone(),
+    );
+    let message = backends.watch(&root);
+
+    let watched_dirs = *watched_dirs.lock().unwrap();
+    let polled = made
+        .load(Ordering::Relaxed)
+        .then(|| polled.lock().unwrap().clone());
+    LimitedWatch {
+        _temp: temp,
+        root,
+        message,
+        watched_dirs,
+        polled,
+    }
+}
+
+#[test]
+fn polls_only_the_subtrees_inotify_ran_out_of_watches_for() {
+    let watch = watch_with_limit("subtrees", 1);
+    assert!(watch
+        .message
+        .is_some_and(|message| message.contains("polling them instead")));
+
+    let expected = WatchedDirs {
+        watched: 1,
+        polled: 6,
+    };
+    assert_eq!(watch.watched_dirs, expected);
+    // Only the root itself got a watch, the poller takes each directory below it with everything inside
+    let mut polled = watch.polled.unwrap();
+    polled.sort();
+    let subtrees = ["a", "a/a1", "a/a2", "b", "b/b1", "c"].map(|dir| watch.root.join(dir));
+    assert_eq!(polled, subtrees);
+}
+
+#[test]
+fn polls_every_directory_inotify_did_not_get_to() {
+    let watch = watch_with_limit("partway", 3);
+    assert!(watch
+        .message
+        .is_some_and(|message| message.contains("polling them instead")));
+
+    let expected = WatchedDirs {
+        watched: 3,
+        polled: 4,
+    };
+    assert_eq!(watch.watched_dirs, expected);
+    // Whatever order the directories are listed in, the root got one of the watches
+    let polled = watch.polled.unwrap();
+    assert_eq!(polled.len(), 4);
+    assert!(!polled.contains(&watch.root));
+}
+
+#[test]
+fn makes_no_poller_while_the_watches_last() {
+    let watch = watch_with_limit("enough", 7);
+    assert_eq!(watch.message, None);
+    let expected = WatchedDirs {
+        watched: 7,
+        polled: 0,
+    };
+    assert_eq!(watch.watched_dirs, expected);
+    assert_eq!(watch.polled, None);
+}
+
+#[test]
+fn auto_watches_local_disks_with_inotify() {
+    let root = std::env::temp_dir().canonicalize().unwrap();
+    assert_eq!(WatchBackend::Auto.resolve(&[root]), WatchBackend::Inotify);
+}
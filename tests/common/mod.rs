@@ -0,0 +1,39 @@
+//! What the integration tests share
+
+use std::path::PathBuf;
+
+/// A directory of its own under the system temp dir, removed with everything in it once dropped (a failed
+/// assertion included)
+pub struct TempDir {
+    pub dir: PathBuf,
+}
+
+impl TempDir {
+    /// An empty directory named after `test` and the process, so runs side by side leave each other alone
+    pub fn new(test: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("live_watch_{}_{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir {
+            dir: dir.canonicalize().unwrap(),
+        }
+    }
+
+    /// Make the directory `name` inside, holding `files` (by path inside it), returns where it is
+    pub fn tree(&self, name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = self.dir.join(name);
+        std::fs::create_dir_all(&root).unwrap();
+        for (path, text) in files {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        }
+        root
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
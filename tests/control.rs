@@ -0,0 +1,148 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use live_watch::changes::ChangeKind;
+use live_watch::control::{self, Control, Reply, Request};
+use live_watch::json_events::JsonEvent;
+
+type Runtime = Arc<Mutex<tokio::runtime::Runtime>>;
+
+fn socket_path(test: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("live_watch_{}_{}.sock", test, std::process::id()))
+}
+
+fn runtime() -> Runtime {
+    Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()))
+}
+
+/// Stands in for the window: answers every request with its own `cmd` as the key
+fn answer_requests(mut control: Control) {
+    std::thread::spawn(move || loop {
+        match control.try_next() {
+            Some(pending) => {
+                let cmd = serde_json::to_value(pending.request_ref()).unwrap()["cmd"].clone();
+                let reply = Reply {
+                    key: Some(cmd.as_str().unwrap().to_owned()),
+                    ..Reply::ok()
+                };
+                pending.reply(reply);
+            }
+            None => std::thread::sleep(Duration::from_millis(5)),
+        }
+    });
+}
+
+#[test]
+fn requests_are_answered_in_order() {
+    let path = socket_path("answered");
+    let rt = runtime();
+    answer_requests(Control::bind(&path, rt.clone()).unwrap());
+
+    let requests = [
+        Request::Select {
+            key: PathBuf::from("w/a.txt"),
+        },
+        Request::Current,
+    ];
+    let replies = control::send(&path, &requests).unwrap();
+
+    let keys: Vec<_> = replies.into_iter().map(|reply| reply.key).collect();
+    assert_eq!(
+        keys,
+        [Some(String::from("select")), Some(String::from("current"))]
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bad_requests_get_an_error() {
+    let path = socket_path("bad");
+    let rt = runtime();
+    let _control = Control::bind(&path, rt.clone()).unwrap();
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    writeln!(stream, r#"{{"cmd":"fly"}}"#).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).unwrap();
+
+    let reply: Reply = serde_json::from_str(&line).unwrap();
+    assert!(!reply.ok);
+    assert!(reply.error.unwrap().starts_with("bad request"));
+}
+
+#[test]
+fn subscribers_get_published_events() {
+    let path = socket_path("subscribe");
+    let rt = runtime();
+    let control = Control::bind(&path, rt.clone()).unwrap();
+
+    let mut stream = UnixStream::connect(&path).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    writeln!(stream, r#"{{"cmd":"subscribe"}}"#).unwrap();
+    let mut lines = BufReader::new(stream).lines();
+    let reply: Reply = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert_eq!(reply, Reply::ok());
+
+    let event = JsonEvent {
+        timestamp: String::from("2026-10-19T07:25:23.418Z"),
+        kind: ChangeKind::Deleted,
+        path: String::from("w/a.txt"),
+        absolute_path: String::from("/tmp/w/a.txt"),
+        from: None,
+        absolute_from: None,
+        size: None,
+        sha256: None,
+        content: None,
+        diff: None,
+    };
+    control.publish(event.clone());
+
+    assert_eq!(lines.next().unwrap().unwrap(), event.to_line());
+}
+
+#[test]
+fn stale_sockets_are_taken_over() {
+    let path = socket_path("stale");
+    let rt = runtime();
+    // Left behind by a window that did not get to clean up
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(Path::new(&path).exists());
+
+    let control = Control::bind(&path, rt.clone()).unwrap();
+    // Taken while it is answered
+    assert!(Control::bind(&path, rt.clone()).is_err());
+
+    drop(control);
+    assert!(!path.exists());
+}
+
+#[test]
+fn sockets_of_other_users_are_left_alone() {
+    let path = socket_path("other_user");
+    let rt = runtime();
+    // Put there by someone else, as far as the check can tell
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    if std::os::unix::fs::chown(&path, Some(65534), Some(65534)).is_err() {
+        // Only root can hand a file to another user
+        std::fs::remove_file(&path).unwrap();
+        return;
+    }
+
+    let err = control::send(&path, &[Request::Current]).unwrap_err();
+    assert!(
+        err.to_string().contains("belongs to another user"),
+        "{}",
+        err
+    );
+    drop(listener);
+    let err = Control::bind(&path, rt.clone()).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    assert!(path.exists());
+
+    std::fs::remove_file(&path).unwrap();
+}
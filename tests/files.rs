@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use live_watch::files::{inside_root, File, TextMode};
+
+#[test]
+fn newline_mode_drops_empty_lines_of_either_ending() {
+    let file = File::from_text(
+        PathBuf::from("a.txt"),
+        String::from("one\n\ntwo\r\n\r\nthree"),
+    );
+    let form = file.file_form_ref();
+    let lines: Vec<&str> = form
+        .get_file_text(&TextMode::Newline)
+        .unwrap()
+        .iter()
+        .map(|segment| form.segment_text(segment))
+        .collect();
+    assert_eq!(lines, ["one\n", "two\r\n", "three"]);
+}
+
+#[test]
+fn appending_splits_lines_like_loading_all_of_it() {
+    let whole = "one\ntw";
+    let mut file = File::from_text(PathBuf::from("<stdin>"), String::from(whole));
+    let version = file.version();
+    file.append("o\n\r\nthree\n");
+    assert!(file.version() > version);
+
+    let loaded = File::from_text(
+        PathBuf::from("<stdin>"),
+        String::from("one\ntwo\n\r\nthree\n"),
+    );
+    let (appended, loaded) = (file.file_form_ref(), loaded.file_form_ref());
+    assert_eq!(appended.text_ref(), loaded.text_ref());
+    assert_eq!(appended.line_count(), loaded.line_count());
+    for text_mode in [TextMode::Standard, TextMode::Newline, TextMode::AllNewline] {
+        assert_eq!(
+            appended.get_file_text(&text_mode),
+            loaded.get_file_text(&text_mode)
+        );
+    }
+}
+
+#[test]
+fn keys_are_matched_inside_their_root() {
+    assert_eq!(
+        inside_root(Path::new("live_watch/src/main.rs")),
+        Path::new("src/main.rs")
+    );
+    // Watched on its own, the key is just the name
+    assert_eq!(inside_root(Path::new("notes.txt")), Path::new("notes.txt"));
+}
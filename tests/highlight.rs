@@ -0,0 +1,141 @@
+#![cfg(feature = "gui")]
+
+use std::path::Path;
+
+use live_watch::live_watch::highlight::{Language, Token};
+
+fn tokens(language: Language, text: &str) -> Vec<(&str, Token)> {
+    language
+        .tokenize(text)
+        .into_iter()
+        .map(|(segment, token)| (&text[segment], token))
+        .collect()
+}
+
+fn strings(language: Language, text: &str) -> Vec<&str> {
+    tokens(language, text)
+        .into_iter()
+        .filter(|(_, token)| *token == Token::String)
+        .map(|(text, _)| text)
+        .collect()
+}
+
+fn comments(language: Language, text: &str) -> Vec<&str> {
+    tokens(language, text)
+        .into_iter()
+        .filter(|(_, token)| *token == Token::Comment)
+        .map(|(text, _)| text)
+        .collect()
+}
+
+#[test]
+fn rust_strings_comments_and_escapes() {
+    let text = "let s = \"a \\\"quoted\\\" // word\"; // said\n/* block\n spans */ let c = '\\'';\nfn f<'a>() {}";
+    assert_eq!(
+        strings(Language::Rust, text),
+        ["\"a \\\"quoted\\\" // word\"", "'\\''"]
+    );
+    assert_eq!(
+        comments(Language::Rust, text),
+        ["// said", "/* block\n spans */"]
+    );
+    // A lifetime is no char literal
+    assert!(tokens(Language::Rust, text).contains(&("'a", Token::Type)));
+    assert_eq!(
+        strings(Language::Rust, "let s = \"one\ntwo\";"),
+        ["\"one\ntwo\""]
+    );
+}
+
+#[test]
+fn toml_strings_comments_keys_and_sections() {
+    let text = "[package]\nname = \"a # b \\\" c\" # comment\nlit = 'c:\\path'";
+    let tokens = tokens(Language::Toml, text);
+    assert!(tokens.contains(&("[package]", Token::Section)));
+    assert!(tokens.contains(&("name", Token::Key)));
+    assert_eq!(
+        strings(Language::Toml, text),
+        ["\"a # b \\\" c\"", "'c:\\path'"]
+    );
+    assert_eq!(comments(Language::Toml, text), ["# comment"]);
+    // Strings end with their line
+    assert_eq!(strings(Language::Toml, "a = \"open\nb = 1"), ["\"open"]);
+}
+
+#[test]
+fn json_strings_escapes_and_keys() {
+    let text = "{\"key\": \"va\\\"lue\", \"n\": null}";
+    let tokens = tokens(Language::Json, text);
+    assert!(tokens.contains(&("\"key\"", Token::Key)));
+    assert!(tokens.contains(&("\"va\\\"lue\"", Token::String)));
+    assert!(tokens.contains(&("null", Token::Literal)));
+    // There are no comments in json
+    assert!(comments(Language::Json, "{\"a\": 1} // no").is_empty());
+}
+
+#[test]
+fn yaml_strings_comments_and_list_keys() {
+    let text = "- name: 'it''s' # comment\n  url: \"http://x/#a\"";
+    let tokens = tokens(Language::Yaml, text);
+    assert!(tokens.contains(&("name", Token::Key)));
+    assert!(tokens.contains(&("url", Token::Key)));
+    assert_eq!(comments(Language::Yaml, text), ["# comment"]);
+    assert!(strings(Language::Yaml, text).contains(&"\"http://x/#a\""));
+}
+
+#[test]
+fn shell_strings_comments_and_variables() {
+    let text = "echo \"$HOME \\\" # not\" # comment\nx=${PATH}#kept";
+    let tokens = tokens(Language::Shell, text);
+    assert_eq!(strings(Language::Shell, text), ["\"$HOME \\\" # not\""]);
+    // A `#` inside a word does not start a comment
+    assert_eq!(comments(Language::Shell, text), ["# comment"]);
+    assert!(tokens.contains(&("${PATH}", Token::Variable)));
+}
+
+#[test]
+fn log_timestamps_levels_and_strings() {
+    let text = "2023-09-21 14:03:11.512 ERROR failed to open \"a b\"\n14:03:12 INFO done";
+    let tokens = tokens(Language::Log, text);
+    assert!(tokens.contains(&("2023-09-21 14:03:11.512", Token::Timestamp)));
+    assert!(tokens.contains(&("ERROR", Token::Error)));
+    assert!(tokens.contains(&("\"a b\"", Token::String)));
+    assert!(tokens.contains(&("14:03:12", Token::Timestamp)));
+    assert!(tokens.contains(&("INFO", Token::Info)));
+}
+
+#[test]
+fn plain_text_has_no_strings_or_comments() {
+    let text = "say \"hi\" # there // 42";
+    assert_eq!(tokens(Language::Plain, text), [("42", Token::Number)]);
+}
+
+#[test]
+fn languages_are_picked_by_name_before_content() {
+    let detect = |path: &str, text: &str| Language::detect(Path::new(path), text);
+    assert_eq!(detect("w/main.rs", "{}"), Language::Rust);
+    assert_eq!(detect("w/Cargo.lock", ""), Language::Toml);
+    assert_eq!(detect("w/.bashrc", ""), Language::Shell);
+    assert_eq!(detect("w/a.jsonl", ""), Language::Json);
+    assert_eq!(detect("w/a.yml", ""), Language::Yaml);
+    assert_eq!(detect("w/a.out", ""), Language::Log);
+}
+
+#[test]
+fn languages_are_sniffed_without_a_known_extension() {
+    let sniff = |text: &str| Language::detect(Path::new("w/README"), text);
+    assert_eq!(sniff(""), Language::Plain);
+    assert_eq!(sniff("#!/bin/bash\necho hi"), Language::Shell);
+    assert_eq!(sniff("#!/usr/bin/env sh\n"), Language::Shell);
+    assert_eq!(sniff("#!/usr/bin/python3\n"), Language::Plain);
+    assert_eq!(sniff("{\"a\": 1}"), Language::Json);
+    assert_eq!(sniff("[\n  {\"a\": 1}\n]"), Language::Json);
+    assert_eq!(sniff("[1, 2]"), Language::Json);
+    assert_eq!(sniff("[package]\nname = \"a\""), Language::Toml);
+    assert_eq!(sniff("---\nname: a\nlist:\n  - b"), Language::Yaml);
+    assert_eq!(
+        sniff("12:00:01 started\n12:00:02 WARN slow\n12:00:03 stopped"),
+        Language::Log
+    );
+    assert_eq!(sniff("Just some words.\nAnd more words."), Language::Plain);
+}
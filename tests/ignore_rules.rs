@@ -0,0 +1,158 @@
+use std::path::{Path, PathBuf};
+
+use live_watch::ignore_rules::IgnoreRules;
+
+mod common;
+use common::TempDir;
+
+/// The rules for `roots`, with a config file of `*.bak` next to them instead of the user's own
+fn rules(temp: &TempDir, roots: &[PathBuf], globs: &[&str]) -> IgnoreRules {
+    let config = temp.dir.join("ignore");
+    std::fs::write(&config, "*.bak\n").unwrap();
+    let globs: Vec<String> = globs.iter().map(|glob| glob.to_string()).collect();
+    IgnoreRules::with_config(roots, Some(&config), &globs).unwrap()
+}
+
+/// Which of `paths` (inside `root`, directories ending in `/`) are ignored
+fn ignored<'a>(rules: &IgnoreRules, root: &Path, paths: &[&'a str]) -> Vec<&'a str> {
+    paths
+        .iter()
+        .copied()
+        .filter(|path| {
+            let is_dir = path.ends_with('/');
+            rules.is_ignored(&root.join(path.trim_end_matches('/')), is_dir)
+        })
+        .collect()
+}
+
+#[test]
+fn defaults_leave_out_build_and_vcs_directories() {
+    let temp = TempDir::new("ignore_defaults");
+    let root = temp.tree("w", &[("src/a.rs", "")]);
+    let rules = rules(&temp, std::slice::from_ref(&root), &[]);
+    let paths = [
+        ".git/",
+        ".git/config",
+        "target/",
+        "target/debug/a",
+        "web/node_modules/x.js",
+        "src/a.rs",
+        "targets.txt",
+    ];
+    assert_eq!(
+        ignored(&rules, &root, &paths),
+        [
+            ".git/",
+            ".git/config",
+            "target/",
+            "target/debug/a",
+            "web/node_modules/x.js"
+        ]
+    );
+}
+
+#[test]
+fn ignore_files_win_over_the_defaults() {
+    let temp = TempDir::new("ignore_files_over_defaults");
+    let root = temp.tree(
+        "w",
+        &[(".gitignore", "!target/\n*.log\n"), (".ignore", "dist/\n")],
+    );
+    let rules = rules(&temp, std::slice::from_ref(&root), &[]);
+    let paths = ["target/", "target/debug/a", "a.log", "dist/b.js", ".git/"];
+    assert_eq!(
+        ignored(&rules, &root, &paths),
+        ["a.log", "dist/b.js", ".git/"]
+    );
+}
+
+#[test]
+fn deeper_ignore_files_win_over_shallower_ones() {
+    let temp = TempDir::new("ignore_deeper_files");
+    let root = temp.tree(
+        "w",
+        &[
+            (".gitignore", "*.log\n"),
+            ("sub/.ignore", "!keep.log\n"),
+            ("sub/deeper/.gitignore", "keep.log\n"),
+        ],
+    );
+    let rules = rules(&temp, std::slice::from_ref(&root), &[]);
+    let paths = [
+        "keep.log",
+        "sub/keep.log",
+        "sub/other.log",
+        "sub/deeper/keep.log",
+    ];
+    assert_eq!(
+        ignored(&rules, &root, &paths),
+        ["keep.log", "sub/other.log", "sub/deeper/keep.log"]
+    );
+}
+
+#[test]
+fn user_globs_win_over_ignore_files_and_defaults() {
+    let temp = TempDir::new("ignore_user_globs");
+    let root = temp.tree("w", &[(".gitignore", "secret.txt\n!*.log\n")]);
+    let rules = rules(
+        &temp,
+        std::slice::from_ref(&root),
+        &["*.log", "!secret.txt", "!target/", "!keep.bak"],
+    );
+    let paths = ["a.log", "sub/b.log", "secret.txt", "target/debug/a"];
+    assert_eq!(ignored(&rules, &root, &paths), ["a.log", "sub/b.log"]);
+    // The config file comes before the command line
+    assert_eq!(ignored(&rules, &root, &["x.bak", "keep.bak"]), ["x.bak"]);
+}
+
+#[test]
+fn ignore_files_of_ignored_directories_are_not_read() {
+    let temp = TempDir::new("ignore_ignored_dirs");
+    let root = temp.tree(
+        "w",
+        &[
+            (".gitignore", "vendor/\n"),
+            ("vendor/.gitignore", "!*\n"),
+            ("vendor/lib.rs", ""),
+        ],
+    );
+    let rules = rules(&temp, std::slice::from_ref(&root), &[]);
+    assert_eq!(
+        ignored(&rules, &root, &["vendor/", "vendor/lib.rs"]),
+        ["vendor/", "vendor/lib.rs"]
+    );
+}
+
+#[test]
+fn rules_stay_with_their_root() {
+    let temp = TempDir::new("ignore_roots");
+    let one = temp.tree("one", &[(".gitignore", "*.tmp\n")]);
+    let two = temp.tree("two", &[(".gitignore", "*.out\n")]);
+    let rules = rules(&temp, &[one.clone(), two.clone()], &["build/"]);
+
+    assert_eq!(
+        ignored(&rules, &one, &["a.tmp", "a.out", "build/"]),
+        ["a.tmp", "build/"]
+    );
+    assert_eq!(
+        ignored(&rules, &two, &["a.tmp", "a.out", "build/"]),
+        ["a.out", "build/"]
+    );
+    // Paths outside every root are never ignored
+    let outside = temp.dir.join("elsewhere");
+    assert!(ignored(&rules, &outside, &["a.tmp", "target/"]).is_empty());
+
+    // Switching roots leaves the ignore files of the old ones behind
+    assert!(rules.switch_roots(std::slice::from_ref(&two)).is_empty());
+    assert!(ignored(&rules, &one, &["a.tmp", "build/"]).is_empty());
+    assert_eq!(ignored(&rules, &two, &["a.out"]), ["a.out"]);
+}
+
+#[test]
+fn broken_lines_are_reported_and_the_rest_still_applies() {
+    let temp = TempDir::new("ignore_broken");
+    let root = temp.tree("w", &[(".gitignore", "a[\n*.log\n")]);
+    let rules = rules(&temp, std::slice::from_ref(&root), &[]);
+    assert_eq!(rules.errors_ref().len(), 1);
+    assert_eq!(ignored(&rules, &root, &["b.log"]), ["b.log"]);
+}
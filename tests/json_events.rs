@@ -0,0 +1,46 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use live_watch::changes::ChangeKind;
+use live_watch::files::FileStore;
+use live_watch::ignore_rules::IgnoreRules;
+use live_watch::json_events::JsonEvent;
+use live_watch::list_filters::ListFilters;
+
+mod common;
+use common::TempDir;
+
+#[test]
+fn files_too_big_to_load_still_have_a_size_and_hash() {
+    let temp = TempDir::new("json_big_files");
+    let root = temp.tree("w", &[("a.txt", "hi\n"), ("big.txt", "hello world\n")]);
+    let roots = [root];
+    let ignore_rules = Arc::new(IgnoreRules::with_config(&roots, None, &[]).unwrap());
+    let mut filters = ListFilters::new();
+    filters.set_max_file_size(Some(10));
+    let store = FileStore::new(&roots, ignore_rules, filters);
+    let file_cache = store.file_cache_ref();
+
+    let big = Path::new("w/big.txt");
+    assert!(file_cache.contains(big) && file_cache.file_ref(big).is_none());
+    let event = JsonEvent::new(ChangeKind::Changed, big, None, file_cache, None, None);
+    assert_eq!(event.size, Some(12));
+    // sha256sum of "hello world\n"
+    assert_eq!(
+        event.sha256.as_deref(),
+        Some("a948904f2f0f479b8f8197694b30184b0d2ed1c1cd2a1ec0fb85d299a192a447")
+    );
+
+    let loaded = JsonEvent::new(
+        ChangeKind::Changed,
+        Path::new("w/a.txt"),
+        None,
+        file_cache,
+        None,
+        None,
+    );
+    assert_eq!(loaded.size, Some(3));
+
+    let deleted = JsonEvent::new(ChangeKind::Deleted, big, None, file_cache, None, None);
+    assert_eq!((deleted.size, deleted.sha256), (None, None));
+}
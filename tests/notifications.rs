@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use live_watch::notifications::{diff_summary, ChangeNotifier, Notification, Notifier};
+use live_watch::MagicError;
+
+/// Keeps every notification instead of talking to a desktop session
+#[derive(Clone, Default)]
+struct MockNotifier {
+    sent: Rc<RefCell<Vec<Notification>>>,
+}
+
+impl Notifier for MockNotifier {
+    fn notify(&mut self, notification: &Notification) -> Result<(), MagicError> {
+        self.sent.borrow_mut().push(notification.clone());
+        Ok(())
+    }
+}
+
+fn notifier(min_interval: Duration) -> (ChangeNotifier, MockNotifier) {
+    let mock = MockNotifier::default();
+    let notifier = ChangeNotifier::new(Box::new(mock.clone()), min_interval);
+    (notifier, mock)
+}
+
+#[test]
+fn notifies_watched_file_with_diff_summary() {
+    let (mut notifier, mock) = notifier(Duration::ZERO);
+    let key = PathBuf::from("project/config.toml");
+    notifier.watch_file(key.clone(), Some("a = 1\nb = 2\n"));
+
+    let sent = notifier.changed(&key, "a = 1\nb = 3\n").unwrap();
+
+    let expected = Notification {
+        summary: String::from("config.toml changed"),
+        body: String::from("+1 -1 lines at line 2: b = 3"),
+    };
+    assert_eq!(sent, Some(expected.clone()));
+    assert_eq!(*mock.sent.borrow(), vec![expected]);
+}
+
+#[test]
+fn ignores_files_that_are_not_watched() {
+    let (mut notifier, mock) = notifier(Duration::ZERO);
+    notifier.watch_file(PathBuf::from("project/config.toml"), None);
+
+    let sent = notifier
+        .changed(Path::new("project/other.toml"), "x")
+        .unwrap();
+
+    assert_eq!(sent, None);
+    assert!(mock.sent.borrow().is_empty());
+}
+
+#[test]
+fn globs_pick_files() {
+    let (mut notifier, mock) = notifier(Duration::ZERO);
+    notifier.set_patterns("*.log").unwrap();
+
+    notifier
+        .changed(Path::new("project/build.log"), "done")
+        .unwrap();
+    notifier
+        .changed(Path::new("project/main.rs"), "fn main() {}")
+        .unwrap();
+
+    let sent = mock.sent.borrow();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].summary, "build.log changed");
+}
+
+#[test]
+fn bad_globs_are_reported() {
+    let (mut notifier, _mock) = notifier(Duration::ZERO);
+    assert!(notifier.set_patterns("[").is_err());
+}
+
+#[test]
+fn rate_limit_is_per_file_and_diffs_from_last_notification() {
+    let (mut notifier, mock) = notifier(Duration::from_millis(200));
+    let log = PathBuf::from("project/app.log");
+    let config = PathBuf::from("project/config.toml");
+    notifier.watch_file(log.clone(), Some("one\n"));
+    notifier.watch_file(config.clone(), Some("a = 1\n"));
+
+    assert!(notifier.changed(&log, "one\ntwo\n").unwrap().is_some());
+    // Too soon for the same file, but not for another one
+    assert!(notifier
+        .changed(&log, "one\ntwo\nthree\n")
+        .unwrap()
+        .is_none());
+    assert!(notifier.changed(&config, "a = 2\n").unwrap().is_some());
+    assert_eq!(mock.sent.borrow().len(), 2);
+
+    // The change held back is part of the next diff, taken against what the last notification showed
+    std::thread::sleep(Duration::from_millis(250));
+    let sent = notifier
+        .changed(&log, "one\ntwo\nthree\nfour\n")
+        .unwrap()
+        .unwrap();
+    assert_eq!(sent.body, "+2 -0 lines at line 3: three");
+}
+
+#[test]
+fn globs_match_inside_the_root() {
+    let (mut notifier, mock) = notifier(Duration::ZERO);
+    notifier.set_patterns("src/*.rs").unwrap();
+
+    notifier
+        .changed(Path::new("project/src/main.rs"), "fn main() {}")
+        .unwrap();
+    notifier
+        .changed(Path::new("project/tests/main.rs"), "fn main() {}")
+        .unwrap();
+
+    let sent = mock.sent.borrow();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].body, "project/src/main.rs");
+}
+
+#[test]
+fn diff_summary_counts_changed_lines() {
+    assert_eq!(diff_summary("a\nb\nc\n", "a\nb\nc\n"), "No line changes");
+    assert_eq!(
+        diff_summary("a\nc\n", "a\nb\nc\n"),
+        "+1 -0 lines at line 2: b"
+    );
+    assert_eq!(diff_summary("a\nb\nc\n", "a\n"), "+0 -2 lines at line 2");
+}
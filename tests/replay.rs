@@ -0,0 +1,177 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use live_watch::changes::Change;
+use live_watch::files::{File, FileStore};
+use live_watch::list_filters::ListFilters;
+use live_watch::watching::backend::WatchBackend;
+use live_watch::watching::session::Session;
+use live_watch::watching::watcher::{WatchEvent, WatchOptions, Watcher};
+
+mod common;
+use common::TempDir;
+
+fn session_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/sessions")
+        .join(format!("{}.jsonl", name))
+}
+
+/// Apply updates until `deadline`
+fn apply_until(
+    deadline: Instant,
+    watcher: &mut Watcher,
+    store: &mut FileStore,
+    rt: &tokio::runtime::Handle,
+) {
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        let next = rt.block_on(async { tokio::time::timeout(left, watcher.next()).await });
+        if let Ok(Some(WatchEvent::Update(update))) = next {
+            store.apply(update);
+        }
+    }
+}
+
+/// Replay the recorded `session` against the tree as it was when recording stopped, `after`, with the store
+/// holding the texts the files had before, `before`
+///
+/// The watcher reads files from disk as events come in, so whatever the events make of the store in between, it has to
+/// end up holding exactly `after`. Returns the changes the pipeline reported along the way, as `kind key`.
+fn replay(session: &str, before: &[(&str, &str)], after: &[(&str, &str)]) -> Vec<String> {
+    let temp = TempDir::new(&format!("replay_{}", session));
+    let root = temp.tree("w", after);
+    let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
+    let mut options = WatchOptions::new();
+    options.replay = Some(session_path(session));
+    let mut watcher = Watcher::new(std::slice::from_ref(&root), &options, rt.clone()).unwrap();
+    assert_eq!(watcher.backend(), WatchBackend::Replay);
+    let mut store = watcher.file_store(ListFilters::new());
+    for (name, text) in before {
+        let file = File::from_text(root.join(name), text.to_string());
+        let key = Path::new("w").join(name);
+        store.file_cache_mut().insert(key, Some(file));
+    }
+
+    // Renames wait a moment for their other half, give them that much on top of the session
+    let length = Session::load(&session_path(session)).unwrap().duration();
+    let deadline = Instant::now() + length + Duration::from_millis(500);
+    let handle = rt.lock().unwrap().handle().clone();
+    let mut changes = Vec::new();
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        let next = handle.block_on(async { tokio::time::timeout(left, watcher.next()).await });
+        if let Ok(Some(WatchEvent::Update(update))) = next {
+            for change in Change::apply(&mut store, update) {
+                changes.push(describe(&change));
+            }
+        }
+    }
+
+    let mut keys: Vec<_> = store.file_cache_ref().keys().cloned().collect();
+    keys.sort();
+    let expected: Vec<_> = after
+        .iter()
+        .map(|(name, _)| Path::new("w").join(name))
+        .collect();
+    assert_eq!(keys, expected);
+    for (name, text) in after {
+        let file = store.file_cache_ref().file_ref(&Path::new("w").join(name));
+        assert_eq!(
+            file.map(|file| file.file_form_ref().text_ref()),
+            Some(*text)
+        );
+    }
+
+    changes
+}
+
+fn describe(change: &Change) -> String {
+    let kind = serde_json::to_value(change.kind()).unwrap();
+    let kind = kind.as_str().unwrap();
+    match change.from_ref() {
+        Some(from) => format!(
+            "{} {} -> {}",
+            kind,
+            from.display(),
+            change.key_ref().display()
+        ),
+        None => format!("{} {}", kind, change.key_ref().display()),
+    }
+}
+
+#[test]
+fn vim_writing_in_place_with_a_swap_file() {
+    let changes = replay(
+        "vim",
+        &[("a.txt", "hello\n")],
+        &[("a.txt", "hello\nworld\n")],
+    );
+    assert_eq!(changes, ["changed w/a.txt"]);
+}
+
+#[test]
+fn vim_renaming_the_original_to_a_backup() {
+    let changes = replay(
+        "vim_backup",
+        &[("a.txt", "hello\n")],
+        &[("a.txt", "hello\nworld\n")],
+    );
+    // The original is kept as a backup while the new text is written, every step of that shows
+    assert_eq!(
+        changes,
+        [
+            "renamed w/a.txt -> w/a.txt~",
+            "created w/a.txt",
+            "deleted w/a.txt~"
+        ]
+    );
+}
+
+#[test]
+fn atomic_write_renaming_a_temporary_file_over_the_original() {
+    let changes = replay(
+        "atomic_write",
+        &[("a.txt", "hello\n")],
+        &[("a.txt", "hello\nworld\n")],
+    );
+    assert_eq!(changes, ["changed w/a.txt"]);
+}
+
+#[test]
+fn sed_in_place() {
+    let changes = replay(
+        "sed_in_place",
+        &[("a.txt", "hello\n")],
+        &[("a.txt", "hello world\n")],
+    );
+    assert_eq!(changes, ["changed w/a.txt"]);
+}
+
+#[test]
+fn recorded_paths_are_relative_to_the_root() {
+    let temp = TempDir::new("replay_record");
+    let root = temp.tree("w", &[("a.txt", "hello\n")]);
+    let recording = temp.dir.join("session.jsonl");
+    let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
+    let mut options = WatchOptions::new();
+    options.record = Some(recording.clone());
+    let mut watcher = Watcher::new(std::slice::from_ref(&root), &options, rt.clone()).unwrap();
+    let mut store = watcher.file_store(ListFilters::new());
+    // Give the watcher a moment to start watching
+    std::thread::sleep(Duration::from_millis(300));
+
+    std::fs::write(root.join("b.txt"), "new\n").unwrap();
+    let handle = rt.lock().unwrap().handle().clone();
+    apply_until(
+        Instant::now() + Duration::from_millis(500),
+        &mut watcher,
+        &mut store,
+        &handle,
+    );
+
+    let session = Session::load(&recording).unwrap();
+    let first = &session.events_ref()[0];
+    assert_eq!(first.at_ms, 0);
+    assert_eq!(first.event.paths, [PathBuf::from("b.txt")]);
+    assert!(first.event.kind.is_create());
+}
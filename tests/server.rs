@@ -0,0 +1,161 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use live_watch::changes::Change;
+use live_watch::json_events::JsonEvent;
+use live_watch::list_filters::ListFilters;
+use live_watch::server::{self, Server};
+use live_watch::watching::watcher::{WatchEvent, WatchOptions, Watcher};
+
+mod common;
+use common::TempDir;
+
+type Runtime = Arc<Mutex<tokio::runtime::Runtime>>;
+
+/// Watch `root` and serve it on a free localhost port, the way `--serve` does
+fn start(root: PathBuf, filters: ListFilters) -> (SocketAddr, Runtime) {
+    let rt = Arc::new(Mutex::new(tokio::runtime::Runtime::new().unwrap()));
+    let mut watcher = Watcher::new(&[root], &WatchOptions::new(), rt.clone()).unwrap();
+    let store = Arc::new(RwLock::new(watcher.file_store(filters)));
+    let server = Arc::new(Server::new(store.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    {
+        let rt = rt.lock().unwrap();
+        rt.spawn(server::serve(server.clone(), listener));
+        rt.spawn(async move {
+            while let Some(event) = watcher.next().await {
+                let WatchEvent::Update(update) = event else {
+                    continue;
+                };
+                let mut store = store.write().unwrap();
+                for change in Change::apply(&mut store, update) {
+                    server.publish(JsonEvent::from_change(
+                        &change,
+                        store.file_cache_ref(),
+                        None,
+                    ));
+                }
+            }
+        });
+    }
+    // Give the watcher a moment to start watching
+    std::thread::sleep(Duration::from_millis(300));
+
+    (addr, rt)
+}
+
+fn request(addr: SocketAddr, path: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    )
+    .unwrap();
+    stream
+}
+
+/// The status line and body of a plain GET
+fn get(addr: SocketAddr, path: &str) -> (String, String) {
+    let mut response = String::new();
+    request(addr, path).read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_owned();
+    (status, body.to_owned())
+}
+
+/// Read from `stream` until what arrived so far contains every one of `needles`, or five seconds passed
+fn read_until(stream: &mut TcpStream, needles: &[&str]) -> String {
+    let start = Instant::now();
+    let mut received = String::new();
+    let mut buffer = [0; 4096];
+    while !needles.iter().all(|needle| received.contains(needle)) {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "never got {:?}, only {:?}",
+            needles,
+            received
+        );
+        let read = stream.read(&mut buffer).unwrap();
+        assert!(
+            read > 0,
+            "closed before {:?}, after {:?}",
+            needles,
+            received
+        );
+        received.push_str(&String::from_utf8_lossy(&buffer[..read]));
+    }
+    received
+}
+
+#[test]
+fn serves_file_list_and_contents() {
+    let temp = TempDir::new("serves_files");
+    let root = temp.tree("w", &[("a.txt", "hello\n")]);
+    let (addr, _rt) = start(root.clone(), ListFilters::new());
+
+    let (status, body) = get(addr, "/files");
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(body, r#"[{"path":"w/a.txt","size":6}]"#);
+
+    let (status, body) = get(addr, "/files/w/a.txt");
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(body, "hello\n");
+
+    let (status, _) = get(addr, "/files/w/missing.txt");
+    assert!(status.contains("404"), "{}", status);
+
+    let (status, body) = get(addr, "/");
+    assert!(status.contains("200"), "{}", status);
+    assert!(body.contains("EventSource"));
+}
+
+#[test]
+fn pushes_changes_as_server_sent_events() {
+    let temp = TempDir::new("pushes_events");
+    let root = temp.tree("w", &[("a.txt", "hello\n")]);
+    let (addr, _rt) = start(root.clone(), ListFilters::new());
+
+    let mut events = request(addr, "/events");
+    let head = read_until(&mut events, &["\r\n\r\n"]);
+    assert!(head.contains("text/event-stream"), "{}", head);
+
+    std::fs::write(root.join("a.txt"), "hello\nworld\n").unwrap();
+    read_until(&mut events, &[r#""kind":"changed""#, r#""path":"w/a.txt""#]);
+
+    std::fs::write(root.join("b.txt"), "new\n").unwrap();
+    read_until(&mut events, &[r#""kind":"created""#, r#""path":"w/b.txt""#]);
+
+    let (_, body) = get(addr, "/files/w/a.txt");
+    assert_eq!(body, "hello\nworld\n");
+}
+
+#[test]
+fn serves_big_files_without_loading_them() {
+    let temp = TempDir::new("serves_big_files");
+    let root = temp.tree("w", &[("a.txt", "hello\n")]);
+    std::fs::write(root.join("big.txt"), "far too big to load\n").unwrap();
+    let mut filters = ListFilters::new();
+    filters.set_max_file_size(Some(10));
+    let (addr, _rt) = start(root.clone(), filters);
+
+    let (status, body) = get(addr, "/files/w/big.txt");
+    assert!(status.contains("200"), "{}", status);
+    assert_eq!(body, "far too big to load\n");
+
+    // Still left out of the cache, so still listed without a size
+    let (_, body) = get(addr, "/files");
+    assert!(
+        body.contains(r#"{"path":"w/big.txt","size":null}"#),
+        "{}",
+        body
+    );
+}
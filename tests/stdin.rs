@@ -0,0 +1,57 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+use serde_json::Value;
+
+#[test]
+fn piped_lines_are_watched_as_they_arrive() {
+    let mut live_watch = Command::new(env!("CARGO_BIN_EXE_live_watch"))
+        .args(["--json", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = live_watch.stdin.take().unwrap();
+    let mut events = BufReader::new(live_watch.stdout.take().unwrap()).lines();
+    let mut next_event =
+        || -> Value { serde_json::from_str(&events.next().unwrap().unwrap()).unwrap() };
+
+    writeln!(stdin, "one").unwrap();
+    let event = next_event();
+    assert_eq!(event["kind"], "changed");
+    assert_eq!(event["path"], "<stdin>");
+    assert_eq!(event["size"], 4);
+
+    writeln!(stdin, "two").unwrap();
+    assert_eq!(next_event()["size"], 8);
+
+    live_watch.kill().unwrap();
+    live_watch.wait().unwrap();
+}
+
+#[test]
+fn characters_cut_off_between_writes_arrive_whole() {
+    let mut live_watch = Command::new(env!("CARGO_BIN_EXE_live_watch"))
+        .args(["--json", "--json-inline", "content", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = live_watch.stdin.take().unwrap();
+    let mut events = BufReader::new(live_watch.stdout.take().unwrap()).lines();
+
+    let e_acute = "é".as_bytes();
+    stdin.write_all(&e_acute[..1]).unwrap();
+    stdin.flush().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    stdin.write_all(&e_acute[1..]).unwrap();
+    writeln!(stdin).unwrap();
+
+    let event: Value = serde_json::from_str(&events.next().unwrap().unwrap()).unwrap();
+    assert_eq!(event["content"], "é\n");
+
+    live_watch.kill().unwrap();
+    live_watch.wait().unwrap();
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use live_watch::triggers::{self, Stream, Trigger, TriggerOutput};
+
+mod common;
+use common::TempDir;
+
+/// Where the next command ran, by what it printed before it finished
+async fn ran_in(output_rx: &mut UnboundedReceiver<TriggerOutput>) -> PathBuf {
+    let mut printed = None;
+    loop {
+        let output = tokio::time::timeout(Duration::from_secs(5), output_rx.recv())
+            .await
+            .expect("no trigger ran")
+            .unwrap();
+        match output {
+            TriggerOutput::Line(Stream::Stdout, line) => printed = Some(PathBuf::from(line)),
+            TriggerOutput::Finished(_) => return printed.expect("nothing printed"),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn triggers_follow_the_roots_they_are_switched_to() {
+    let temp = TempDir::new("triggers_switch");
+    let one = temp.tree("one", &[("a.txt", "")]);
+    let two = temp.tree("two", &[("b.txt", "")]);
+
+    let trigger = Trigger::new("*.txt", "pwd").unwrap();
+    let roots = Arc::new(RwLock::new(vec![one.clone()]));
+    let (batch_tx, batch_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (output_tx, mut output_rx) = tokio::sync::mpsc::unbounded_channel();
+    let (err_tx, _err_rx) = tokio::sync::mpsc::channel(16);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.spawn(triggers::run_triggers(
+        Arc::new(Mutex::new(vec![trigger])),
+        roots.clone(),
+        batch_rx,
+        output_tx,
+        err_tx,
+    ));
+
+    batch_tx.send(vec![one.join("a.txt")]).unwrap();
+    assert_eq!(rt.block_on(ran_in(&mut output_rx)), one);
+
+    *roots.write().unwrap() = vec![two.clone()];
+    batch_tx.send(vec![two.join("b.txt")]).unwrap();
+    assert_eq!(rt.block_on(ran_in(&mut output_rx)), two);
+}